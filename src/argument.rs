@@ -0,0 +1,83 @@
+use crate::{parser::Expr, interner::Interner, logic::Logic, runtime::{self, Table}, utils};
+
+fn cell(head: &str, sym: char) -> String {
+    format!("|{:^1$}| ", sym, head.chars().count() - 3)
+}
+
+// Prints the critical rows, where every premise is true, and returns whether the conclusion is true in all of them
+pub fn check(premises: &[Expr], conclusion: &Expr, interned: &Interner, is_num_symbols: &bool, logic: Logic) -> Result<bool, String> {
+    let mut atoms = premises
+        .iter()
+        .chain(std::iter::once(conclusion))
+        .flat_map(runtime::free_atoms)
+        .collect::<Vec<_>>();
+    atoms.sort();
+    atoms.dedup();
+    atoms.reverse();
+
+    let mut exprs = atoms.iter().map(|n| Expr::Primary(*n)).collect::<Vec<_>>();
+    exprs.extend(premises.iter().cloned());
+    exprs.push(conclusion.clone());
+    let mut table = Table::with_logic(interned, is_num_symbols, logic);
+    let columns = table.evaluate_all(&exprs)?;
+    let rows = columns.last().map_or(0, |c| c.len());
+    let premise_columns = &columns[atoms.len()..columns.len() - 1];
+    let conclusion_column = &columns[columns.len() - 1];
+
+    let critical = (0..rows)
+        .filter(|i| premise_columns.iter().all(|c| c[*i] == logic.top()))
+        .collect::<Vec<_>>();
+    if critical.is_empty() {
+        println!("no critical rows: the premises are never all true");
+        return Ok(true);
+    }
+
+    let headers = exprs.iter().map(|e| format!("[ {} ] ", utils::expr_to_string(e, interned))).collect::<Vec<_>>();
+    let total = headers.iter().map(|h| h.chars().count()).sum::<usize>();
+    println!("{:-<1$}", "", total - 1);
+    println!("{}", headers.concat().trim_end());
+    println!("{}", headers.iter().map(|h| format!("|{:-<1$}| ", "", h.chars().count() - 3)).collect::<String>().trim_end());
+    for i in &critical {
+        let mut line = headers.iter().zip(columns.iter()).map(|(h, c)| cell(h, table.symbol(c[*i]))).collect::<String>();
+        if conclusion_column[*i] != logic.top() {
+            line.push_str("<- counterexample");
+        }
+        println!("{}", line.trim_end());
+    }
+    println!("{:-<1$}", "", total - 1);
+    Ok(critical.iter().all(|i| conclusion_column[*i] == logic.top()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    fn is_valid(input: &str, logic: Logic) -> bool {
+        let mut interned = Interner::new();
+        let Ok(Expr::Argument(premises, conclusion)) = parser::parse_line(input, &mut interned, &History::new()) else {
+            panic!("expected an argument");
+        };
+        check(&premises, &conclusion, &interned, &true, logic).unwrap()
+    }
+
+    #[test]
+    fn valid_and_invalid_arguments() {
+        assert!(is_valid("p -> q, p |- q", Logic::Classical));
+        assert!(is_valid("p | q, ~p therefore q", Logic::Classical));
+        assert!(!is_valid("p -> q, q |- p", Logic::Classical));
+        assert!(is_valid("|- p | ~p", Logic::Classical));
+    }
+
+    #[test]
+    fn inconsistent_premises_have_no_critical_rows() {
+        assert!(is_valid("p, ~p |- q", Logic::Classical));
+    }
+
+    #[test]
+    fn validity_depends_on_the_logic() {
+        assert!(!is_valid("|- p | ~p", Logic::Kleene));
+        assert!(is_valid("p -> q, p |- q", Logic::Kleene));
+        assert!(!is_valid("p |- q -> q", Logic::Kleene));
+    }
+}
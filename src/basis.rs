@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use crate::{parser::{self, Expr}, interner::Interner, runtime};
+
+// Each pass rewrites the connectives outside the basis into the ones inside it, the first
+// template that matches a sub-expression is used. Pattern variables p and q are atoms 0 and 1.
+const NOT_AND: &[&str] = &[
+    "disjunction := p | q = ~(~p & ~q)",
+    "conditional := p -> q = ~(p & ~q)",
+    "biconditional := p <-> q = ~(p & ~q) & ~(q & ~p)",
+    "verum := 1 = ~(p & ~p)",
+    "falsum := 0 = p & ~p",
+];
+
+const NOT_OR: &[&str] = &[
+    "conjunction := p & q = ~(~p | ~q)",
+    "conditional := p -> q = ~p | q",
+    "biconditional := p <-> q = ~(~(~p | q) | ~(~q | p))",
+    "verum := 1 = p | ~p",
+    "falsum := 0 = ~(p | ~p)",
+];
+
+const NOT_ARROW: &[&str] = &[
+    "conjunction := p & q = ~(p -> ~q)",
+    "disjunction := p | q = ~p -> q",
+    "biconditional := p <-> q = ~((p -> q) -> ~(q -> p))",
+    "verum := 1 = p -> p",
+    "falsum := 0 = ~(p -> p)",
+];
+
+// Applied after NOT_AND, every '~(x & y)' is a NAND gate
+const NAND: &[&str] = &[
+    "nand := ~(p & q) = ~(p & q)",
+    "negation := ~p = ~(p & p)",
+    "conjunction := p & q = ~(~(p & q) & ~(p & q))",
+];
+
+// Applied after NOT_OR, every '~(x | y)' is a NOR gate
+const NOR: &[&str] = &[
+    "nor := ~(p | q) = ~(p | q)",
+    "negation := ~p = ~(p | p)",
+    "disjunction := p | q = ~(~(p | q) | ~(p | q))",
+];
+
+pub const BASES: &str = "{~, &}, {~, |}, {~, ->}, nand, nor";
+
+fn passes(basis: &str) -> Option<Vec<&'static [&'static str]>> {
+    let mut names = basis
+        .trim_matches(|c| c == '{' || c == '}')
+        .split(',')
+        .map(|c| c.trim())
+        .collect::<Vec<_>>();
+    names.sort();
+    match names.as_slice() {
+        ["&", "~"] => Some(vec![NOT_AND]),
+        ["|", "~"] => Some(vec![NOT_OR]),
+        ["->", "~"] => Some(vec![NOT_ARROW]),
+        ["nand"] => Some(vec![NOT_AND, NAND]),
+        ["nor"] => Some(vec![NOT_OR, NOR]),
+        _ => None,
+    }
+}
+
+fn convert(expr: &Expr, templates: &[(Expr, Expr)], atom: &Option<Expr>) -> Result<Expr, String> {
+    for (lhs, rhs) in templates {
+        if let Ok(bound) = runtime::match_pattern(expr, lhs) {
+            let mut patterns = HashMap::new();
+            for (var, e) in bound {
+                patterns.insert(var, convert(&e, templates, atom)?);
+            }
+            // Constants have nothing to bind p to, so it becomes an atom in scope, the template is constant for any atom
+            if let Expr::Constant(_) = lhs {
+                match atom {
+                    Some(a) => patterns.insert(Expr::Primary(0), a.clone()),
+                    None => return Err("Constants can only be rewritten where an atom is in scope".to_string()),
+                };
+            }
+            return Ok(runtime::subsitute_in(rhs, &mut patterns)?);
+        }
+    }
+
+    match expr {
+        Expr::Binary(l, op, r) => Ok(Expr::Binary(Box::new(convert(l, templates, atom)?), *op, Box::new(convert(r, templates, atom)?))),
+        Expr::Not(e) => Ok(Expr::Not(Box::new(convert(e, templates, atom)?))),
+        Expr::Group(e) => convert(e, templates, atom),
+        // Without a free atom, the bound atom stands in for constants in the body
+        Expr::Quantified(q, n, e) => {
+            let atom = atom.clone().or(Some(Expr::Primary(*n)));
+            Ok(Expr::Quantified(*q, *n, Box::new(convert(e, templates, &atom)?)))
+        },
+        other => Ok(other.clone()),
+    }
+}
+
+pub fn to_basis(expr: &Expr, basis: &str) -> Result<Expr, String> {
+    let Some(passes) = passes(basis) else {
+        return Err(format!("Unknown basis '{}', expected one of {}", basis, BASES));
+    };
+    let atom = runtime::free_atoms(expr).first().map(|n| Expr::Primary(*n));
+    let mut result = expr.clone();
+    for pass in passes {
+        let mut interned = Interner::new();
+        interned.intern("p");
+        interned.intern("q");
+        let templates = pass
+            .iter()
+            .map(|t| parser::parse_rule(t, &mut interned))
+            .collect::<Result<Vec<_>, _>>()?;
+        result = convert(&result, &templates, &atom)?;
+    }
+    Ok(result)
+}
+
+// A connective as its truth table, row i holds the value for the inputs given by the bits of i
+struct Connective {
+    arity: usize,
+    table: Vec<bool>,
+}
+
+fn connective(name: &str) -> Option<Connective> {
+    let binary = |f: fn(bool, bool) -> bool| (2, (0..4).map(|i| f(i & 2 != 0, i & 1 != 0)).collect::<Vec<_>>());
+    let (arity, table) = match name {
+        "~" | "not" => (1, vec![true, false]),
+        "&" | "and" => binary(|a, b| a && b),
+        "|" | "or" => binary(|a, b| a || b),
+        "->" | "implies" => binary(|a, b| !a || b),
+        "<->" | "equiv" => binary(|a, b| a == b),
+        "nand" => binary(|a, b| !(a && b)),
+        "nor" => binary(|a, b| !(a || b)),
+        "xor" => binary(|a, b| a != b),
+        "1" | "true" => (0, vec![true]),
+        "0" | "false" => (0, vec![false]),
+        _ => return None,
+    };
+    Some(Connective { arity, table })
+}
+
+impl Connective {
+    fn preserves_false(&self) -> bool {
+        !self.table[0]
+    }
+
+    fn preserves_true(&self) -> bool {
+        self.table[self.table.len() - 1]
+    }
+
+    fn is_self_dual(&self) -> bool {
+        let all = self.table.len() - 1;
+        (0..self.table.len()).all(|i| self.table[i ^ all] != self.table[i])
+    }
+
+    fn is_monotone(&self) -> bool {
+        (0..self.table.len()).all(|i| {
+            (0..self.table.len()).all(|j| i & j != i || !self.table[i] || self.table[j])
+        })
+    }
+
+    // Affine functions are xors of their inputs and a constant, which for at most two inputs
+    // means that the algebraic normal form has no term x & y
+    fn is_affine(&self) -> bool {
+        self.arity < 2 || !self.table.iter().fold(false, |acc, v| acc ^ v)
+    }
+}
+
+type Class = (&'static str, fn(&Connective) -> bool);
+
+// Post's criterion: a set is functionally complete iff it is not contained in one of the five
+// maximal clones, i.e. for every class there is a connective outside of it
+pub fn check_completeness(set: &str) -> Result<(bool, Vec<&'static str>), String> {
+    let connectives = set
+        .trim_matches(|c| c == '{' || c == '}')
+        .split(',')
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(|c| connective(c).ok_or(format!("Unknown connective '{}'", c)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if connectives.is_empty() {
+        return Err("Expected a set of connectives such as {~, &}".to_string());
+    }
+
+    let classes: [Class; 5] = [
+        ("preserve 0", Connective::preserves_false),
+        ("preserve 1", Connective::preserves_true),
+        ("are self-dual", Connective::is_self_dual),
+        ("are monotone", Connective::is_monotone),
+        ("are affine", Connective::is_affine),
+    ];
+    let closed = classes
+        .iter()
+        .filter(|(_, member)| connectives.iter().all(member))
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    Ok((closed.is_empty(), closed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser::BinOperator, utils};
+
+    // The connectives of an expression, in the notation of the bases
+    fn used(expr: &Expr, out: &mut Vec<String>) {
+        let mut add = |s: String| if !out.contains(&s) { out.push(s) };
+        match expr {
+            Expr::Binary(l, op, r) => {
+                add(op.to_string());
+                used(l, out);
+                used(r, out);
+            },
+            Expr::Not(e) => {
+                add("~".to_string());
+                used(e, out);
+            },
+            Expr::Group(e) => used(e, out),
+            Expr::Constant(b) => add((*b as usize).to_string()),
+            _ => {},
+        }
+    }
+
+    fn convert_str(input: &str, basis: &str) -> (Vec<String>, bool) {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula(input, &mut interned, &History::new()).unwrap();
+        let res = to_basis(&expr, basis).unwrap();
+        let mut ops = Vec::new();
+        used(&res, &mut ops);
+        ops.sort();
+        (ops, runtime::is_equivalent(&expr, &res, &interned).unwrap())
+    }
+
+    #[test]
+    fn rewrites_into_every_basis() {
+        let input = "(a -> b) <-> (c | ~a) & 1";
+        assert_eq!(convert_str(input, "{~, &}"), (vec!["&".to_string(), "~".to_string()], true));
+        assert_eq!(convert_str(input, "{|, ~}"), (vec!["|".to_string(), "~".to_string()], true));
+        assert_eq!(convert_str(input, "{~, ->}"), (vec!["->".to_string(), "~".to_string()], true));
+    }
+
+    #[test]
+    fn gates_are_negated_connectives() {
+        for (basis, op) in [("nand", BinOperator::And), ("nor", BinOperator::Or)] {
+            let mut interned = Interner::new();
+            let expr = parser::parse_formula("~a | (b -> c) & 0", &mut interned, &History::new()).unwrap();
+            let res = to_basis(&expr, basis).unwrap();
+            assert!(runtime::is_equivalent(&expr, &res, &interned).unwrap());
+            fn gates_only(e: &Expr, op: BinOperator) -> bool {
+                match e {
+                    Expr::Not(inner) => matches!(&**inner, Expr::Binary(l, o, r) if *o == op && gates_only(l, op) && gates_only(r, op)),
+                    Expr::Primary(_) => true,
+                    _ => false,
+                }
+            }
+            assert!(gates_only(&runtime::strip_groups(&res), op), "{:?}", res);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_bases_and_atomless_constants() {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula("1 & 0", &mut interned, &History::new()).unwrap();
+        assert!(to_basis(&expr, "{~, &}").is_err());
+        assert!(to_basis(&expr, "{&, |}").is_err());
+    }
+
+    #[test]
+    fn bound_atoms_stand_in_for_constants_in_their_scope() {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula("forall p. p & 1", &mut interned, &History::new()).unwrap();
+        let res = to_basis(&expr, "{~, &}").unwrap();
+        assert_eq!(utils::expr_to_string(&res, &interned), "forall p. p & ~(p & ~p)");
+        assert!(runtime::is_equivalent(&expr, &res, &interned).unwrap());
+        let outside = parser::parse_formula("1 & exists p. p", &mut interned, &History::new()).unwrap();
+        assert!(to_basis(&outside, "{~, &}").is_err());
+    }
+
+    #[test]
+    fn post_criterion() {
+        assert_eq!(check_completeness("{~, &}"), Ok((true, vec![])));
+        assert_eq!(check_completeness("{nand}"), Ok((true, vec![])));
+        assert_eq!(check_completeness("{->, 0}"), Ok((true, vec![])));
+        assert_eq!(check_completeness("{&, |}"), Ok((false, vec!["preserve 0", "preserve 1", "are monotone"])));
+        assert_eq!(check_completeness("{<->, ~}"), Ok((false, vec!["are affine"])));
+        assert_eq!(check_completeness("{xor, 1}").map(|r| r.0), Ok(false));
+        assert!(check_completeness("{}").is_err());
+        assert!(check_completeness("{&, nope}").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use crate::{parser::{Expr, BinOperator, Quantifier}, runtime};
+
+const FALSE: usize = 0;
+const TRUE: usize = 1;
+
+// Reduced ordered binary decision diagram, variables are tested in the order of `vars`
+#[derive(Debug)]
+pub struct Bdd {
+    vars: Vec<usize>,
+    nodes: Vec<(usize, usize, usize)>,
+    unique: HashMap<(usize, usize, usize), usize>,
+    memo: HashMap<(BinOperator, usize, usize), usize>,
+    pub root: usize,
+}
+
+impl Bdd {
+    pub fn new(expr: &Expr) -> Result<Self, String> {
+        let mut vars = runtime::free_atoms(expr);
+        vars.sort();
+        // Terminals sit below every variable level
+        let terminal = vars.len();
+        let mut bdd = Self {
+            vars,
+            nodes: vec![(terminal, FALSE, FALSE), (terminal, TRUE, TRUE)],
+            unique: HashMap::new(),
+            memo: HashMap::new(),
+            root: FALSE,
+        };
+        bdd.root = bdd.build(expr)?;
+        Ok(bdd)
+    }
+
+    pub fn vars(&self) -> &[usize] {
+        &self.vars
+    }
+
+    fn level(&self, node: usize) -> usize {
+        self.nodes[node].0
+    }
+
+    fn make(&mut self, level: usize, low: usize, high: usize) -> usize {
+        if low == high {
+            return low;
+        }
+        if let Some(n) = self.unique.get(&(level, low, high)) {
+            return *n;
+        }
+        self.nodes.push((level, low, high));
+        self.unique.insert((level, low, high), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn cofactors(&self, node: usize, level: usize) -> (usize, usize) {
+        let (l, low, high) = self.nodes[node];
+        if l == level { (low, high) } else { (node, node) }
+    }
+
+    fn apply(&mut self, op: BinOperator, a: usize, b: usize) -> usize {
+        if a <= TRUE && b <= TRUE {
+            let (x, y) = (a == TRUE, b == TRUE);
+            let res = match op {
+                BinOperator::And => x && y,
+                BinOperator::Or => x || y,
+                BinOperator::Arrow => !x || y,
+                BinOperator::TwinArrow => x == y,
+            };
+            return res as usize;
+        }
+        if let Some(n) = self.memo.get(&(op, a, b)) {
+            return *n;
+        }
+        let level = self.level(a).min(self.level(b));
+        let (a_low, a_high) = self.cofactors(a, level);
+        let (b_low, b_high) = self.cofactors(b, level);
+        let low = self.apply(op, a_low, b_low);
+        let high = self.apply(op, a_high, b_high);
+        let res = self.make(level, low, high);
+        self.memo.insert((op, a, b), res);
+        res
+    }
+
+    fn negate(&mut self, a: usize) -> usize {
+        // a <-> 0 is the negation of a
+        self.apply(BinOperator::TwinArrow, a, FALSE)
+    }
+
+    fn build(&mut self, expr: &Expr) -> Result<usize, String> {
+        match expr {
+            Expr::Binary(l, op, r) => {
+                let left = self.build(l)?;
+                let right = self.build(r)?;
+                Ok(self.apply(*op, left, right))
+            },
+            Expr::Not(e) => {
+                let inner = self.build(e)?;
+                Ok(self.negate(inner))
+            },
+            Expr::Group(e) => self.build(e),
+            Expr::Quantified(q, n, e) => {
+                let low = self.build(&runtime::instantiate(e, *n, &Expr::Constant(false)))?;
+                let high = self.build(&runtime::instantiate(e, *n, &Expr::Constant(true)))?;
+                let op = match q {
+                    Quantifier::Forall => BinOperator::And,
+                    Quantifier::Exists => BinOperator::Or,
+                };
+                Ok(self.apply(op, low, high))
+            },
+            Expr::Primary(n) => {
+                let level = self.vars.iter().position(|v| v == n).unwrap_or_default();
+                Ok(self.make(level, FALSE, TRUE))
+            },
+            Expr::Constant(b) => Ok(*b as usize),
+            _ => Err("Only propositional expressions can be compiled".to_string()),
+        }
+    }
+
+    // Number of satisfying assignments of all variables from `level` downwards
+    fn count_from(&self, node: usize, level: usize, memo: &mut HashMap<usize, u128>) -> u128 {
+        let node_level = self.level(node);
+        let skipped = 1u128 << (node_level - level);
+        if node <= TRUE {
+            return node as u128 * skipped;
+        }
+        if let Some(c) = memo.get(&node) {
+            return c * skipped;
+        }
+        let (_, low, high) = self.nodes[node];
+        let c = self.count_from(low, node_level + 1, memo) + self.count_from(high, node_level + 1, memo);
+        memo.insert(node, c);
+        c * skipped
+    }
+
+    pub fn count(&self) -> Result<u128, String> {
+        if self.vars.len() > 127 {
+            return Err(format!("Too many atoms to count models: {}", self.vars.len()));
+        }
+        Ok(self.count_from(self.root, 0, &mut HashMap::new()))
+    }
+
+    // `probs[i]` is the probability that variable `vars[i]` is true
+    pub fn probability(&self, probs: &[f64]) -> f64 {
+        let mut memo: HashMap<usize, f64> = HashMap::new();
+        self.probability_of(self.root, probs, &mut memo)
+    }
+
+    fn probability_of(&self, node: usize, probs: &[f64], memo: &mut HashMap<usize, f64>) -> f64 {
+        if node <= TRUE {
+            return node as f64;
+        }
+        if let Some(p) = memo.get(&node) {
+            return *p;
+        }
+        let (level, low, high) = self.nodes[node];
+        let p = (1.0 - probs[level]) * self.probability_of(low, probs, memo)
+            + probs[level] * self.probability_of(high, probs, memo);
+        memo.insert(node, p);
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, interner::Interner, parser, runtime::Table};
+
+    fn bdd(input: &str, interned: &mut Interner) -> (Expr, Bdd) {
+        let expr = parser::parse_formula(input, interned, &History::new()).unwrap();
+        let bdd = Bdd::new(&expr).unwrap();
+        (expr, bdd)
+    }
+
+    #[test]
+    fn counts_agree_with_the_truth_table() {
+        for input in ["a & b", "a | b | c", "a -> b", "(a <-> b) & ~c", "a | ~a", "a & ~a", "exists b. a & b", "forall a. a | b"] {
+            let mut interned = Interner::new();
+            let (expr, bdd) = bdd(input, &mut interned);
+            let mut table = Table::new(&interned, &true);
+            table.generate_truthtable(expr).unwrap();
+            let models = table.result().iter().filter(|v| **v == 1).count() as u128;
+            assert_eq!(bdd.count(), Ok(models), "{}", input);
+        }
+    }
+
+    // The diagram below a node, as nested if-then-else over the variables
+    fn shape(bdd: &Bdd, node: usize) -> String {
+        if node <= TRUE {
+            return node.to_string();
+        }
+        let (level, low, high) = bdd.nodes[node];
+        let var = (b'a' + bdd.vars[level] as u8) as char;
+        let side = |n: usize| if n <= TRUE { shape(bdd, n) } else { format!("({})", shape(bdd, n)) };
+        format!("{} ? {} : {}", var, side(high), side(low))
+    }
+
+    #[test]
+    fn equivalent_formulas_reduce_to_the_same_diagram() {
+        let mut interned = Interner::new();
+        assert_eq!(bdd("a | ~a", &mut interned).1.root, TRUE);
+        assert_eq!(bdd("a & ~a", &mut interned).1.root, FALSE);
+        let (_, left) = bdd("~(a & b)", &mut interned);
+        let (_, right) = bdd("~a | ~b", &mut interned);
+        assert_eq!(shape(&left, left.root), shape(&right, right.root));
+        assert_eq!(shape(&left, left.root), "a ? (b ? 0 : 1) : 1");
+    }
+
+    #[test]
+    fn counts_beyond_the_truth_table() {
+        let mut interned = Interner::new();
+        let names = (0..40).map(|i| format!("x{}", "a".repeat(i + 1))).collect::<Vec<_>>();
+        let (_, all) = bdd(&names.join(" & "), &mut interned);
+        assert_eq!(all.count(), Ok(1));
+        let (_, any) = bdd(&names.join(" | "), &mut interned);
+        assert_eq!(any.count(), Ok((1u128 << 40) - 1));
+    }
+
+    #[test]
+    fn probability_weights_each_branch() {
+        let mut interned = Interner::new();
+        let (_, bdd) = bdd("a | b", &mut interned);
+        assert!((bdd.probability(&[0.5, 0.5]) - 0.75).abs() < 1e-12);
+        assert!((bdd.probability(&[0.0, 0.3]) - 0.3).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::{HashMap, VecDeque};
+use crate::{parser::{Expr, Rule}, interner::Interner, runtime, simplify};
+
+const MAX_DEPTH: usize = 8;
+const MAX_VISITED: usize = 20000;
+// Intermediate expressions may grow this much beyond the larger of start and goal
+const COST_SLACK: usize = 4;
+
+// Breadth-first search over single rule applications, so the chain that is found is a shortest one.
+// Returns the expressions after the start, each with the rule that produced it
+pub fn find(start: &Expr, goal: &Expr, rule_bindings: &HashMap<String, Rule>, interned: &Interner) -> Result<Vec<(Expr, String)>, String> {
+    if !runtime::is_equivalent(start, goal, interned)? {
+        return Err("The expressions are not equivalent, so no chain exists".to_string());
+    }
+    let mut rules = rule_bindings
+        .iter()
+        .filter_map(|(name, rule)| match rule {
+            Rule::Equivalence(lhs, rhs) => Some((name.clone(), runtime::strip_groups(lhs), runtime::strip_groups(rhs))),
+            Rule::RuleId(_) => None,
+        })
+        .collect::<Vec<_>>();
+    rules.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let start = runtime::strip_groups(start);
+    let goal = runtime::strip_groups(goal);
+    let limit = simplify::cost(&start).0.max(simplify::cost(&goal).0) + COST_SLACK;
+    let mut parents: HashMap<Expr, Option<(Expr, String)>> = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(start.clone(), None);
+    queue.push_back((start, 0));
+
+    while let Some((e, depth)) = queue.pop_front() {
+        if e == goal {
+            let mut chain = Vec::new();
+            let mut current = e;
+            while let Some(Some((parent, name))) = parents.get(&current) {
+                chain.push((current.clone(), name.clone()));
+                current = parent.clone();
+            }
+            chain.reverse();
+            return Ok(chain);
+        }
+        if depth == MAX_DEPTH || parents.len() >= MAX_VISITED {
+            continue;
+        }
+        for (name, lhs, rhs) in &rules {
+            let rewrites = runtime::rewrite_subterms(&e, lhs, rhs)
+                .into_iter()
+                .chain(runtime::rewrite_subterms(&e, rhs, lhs));
+            for next in rewrites {
+                let next = runtime::strip_groups(&next);
+                if simplify::cost(&next).0 <= limit && !parents.contains_key(&next) {
+                    parents.insert(next.clone(), Some((e.clone(), name.clone())));
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+    }
+    Err(format!("No chain found within {} steps and {} expressions", MAX_DEPTH, MAX_VISITED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser, stdlib, utils};
+
+    fn chain(start: &str, goal: &str) -> Result<Vec<String>, String> {
+        let mut interned = Interner::new();
+        let mut rule_bindings = HashMap::new();
+        stdlib::load(&mut rule_bindings);
+        let start = parser::parse_formula(start, &mut interned, &History::new())?;
+        let goal = parser::parse_formula(goal, &mut interned, &History::new())?;
+        let steps = find(&start, &goal, &rule_bindings, &interned)?;
+        Ok(steps.iter().map(|(e, name)| format!("{} [{}]", utils::expr_to_string(e, &interned), name)).collect())
+    }
+
+    // Rules are tried in the order of their names, which decides between chains of the same length
+    #[test]
+    fn finds_a_shortest_chain() {
+        assert_eq!(chain("~(a & b)", "~b | ~a"), Ok(vec![
+            "~(b & a) [std::commAnd]".to_string(),
+            "~b | ~a [std::demorganAnd]".to_string(),
+        ]));
+        assert_eq!(chain("a -> b", "~b -> ~a"), Ok(vec!["~b -> ~a [std::contraposition]".to_string()]));
+    }
+
+    #[test]
+    fn equal_expressions_need_no_steps() {
+        assert_eq!(chain("a & (b)", "(a) & b"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_expressions_that_are_not_equivalent() {
+        assert_eq!(chain("a & b", "a | b"), Err("The expressions are not equivalent, so no chain exists".to_string()));
+    }
+}
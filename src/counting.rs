@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use crate::{parser::Expr, interner::Interner, runtime::{self, Table}, bdd::Bdd};
+
+// Above this number of atoms the truth table gets too large and the formula is compiled into a BDD
+const TABLE_LIMIT: usize = 16;
+
+pub enum Method {
+    Table,
+    Bdd,
+}
+
+pub struct Count {
+    pub models: u128,
+    pub atoms: usize,
+    pub method: Method,
+}
+
+// The BDD is only built when the truth table would be too large
+pub fn count(expr: &Expr, interned: &Interner) -> Result<Count, String> {
+    if runtime::free_atoms(expr).len() <= TABLE_LIMIT {
+        let mut table = Table::new(interned, &true);
+        table.generate_truthtable(expr.clone())?;
+        let models = table.result().iter().filter(|v| **v == 1).count() as u128;
+        return Ok(Count { models, atoms: table.atoms().len(), method: Method::Table });
+    }
+    let bdd = Bdd::new(expr)?;
+    Ok(Count { models: bdd.count()?, atoms: bdd.vars().len(), method: Method::Bdd })
+}
+
+pub fn probability(expr: &Expr, probs: &HashMap<usize, f64>, interned: &Interner) -> Result<(f64, Method), String> {
+    let mut vars = runtime::free_atoms(expr);
+    vars.sort();
+    let atom_probs = vars
+        .iter()
+        .map(|v| probs.get(v).copied().ok_or(format!("No probability given for atom {}", interned[*v])))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if vars.len() > TABLE_LIMIT {
+        return Ok((Bdd::new(expr)?.probability(&atom_probs), Method::Bdd));
+    }
+    let mut table = Table::new(interned, &true);
+    table.generate_truthtable(expr.clone())?;
+    // Table atoms and `vars` are both sorted by index, row i assigns bit j to atom j
+    let p = table.result()
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| **v == 1)
+        .map(|(i, _)| {
+            atom_probs
+                .iter()
+                .enumerate()
+                .map(|(j, p)| if i & (1 << j) != 0 { *p } else { 1.0 - p })
+                .product::<f64>()
+        })
+        .sum();
+    Ok((p, Method::Table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser, utils};
+
+    fn parse(input: &str, interned: &mut Interner) -> Expr {
+        parser::parse_formula(input, interned, &History::new()).unwrap()
+    }
+
+    #[test]
+    fn small_formulas_use_the_truth_table() {
+        let mut interned = Interner::new();
+        let res = count(&parse("a -> b", &mut interned), &interned).unwrap();
+        assert_eq!((res.models, res.atoms), (3, 2));
+        assert!(matches!(res.method, Method::Table));
+    }
+
+    #[test]
+    fn large_formulas_use_the_bdd() {
+        let mut interned = Interner::new();
+        let names = (0..20).map(|i| format!("x{}", "a".repeat(i + 1))).collect::<Vec<_>>();
+        let res = count(&parse(&names.join(" | "), &mut interned), &interned).unwrap();
+        assert_eq!((res.models, res.atoms), ((1 << 20) - 1, 20));
+        assert!(matches!(res.method, Method::Bdd));
+    }
+
+    #[test]
+    fn probability_by_table_and_by_bdd_agree() {
+        let mut interned = Interner::new();
+        let expr = parse("(a & b) | (~a & c)", &mut interned);
+        let probs = utils::parse_assignments("a=0.2, b=0.5, c=0.9", &mut interned).unwrap();
+        let (p, _) = probability(&expr, &probs, &interned).unwrap();
+        assert!((p - (0.2 * 0.5 + 0.8 * 0.9)).abs() < 1e-12);
+        let bdd = Bdd::new(&expr).unwrap();
+        let ordered = bdd.vars().iter().map(|v| probs[v]).collect::<Vec<_>>();
+        assert!((bdd.probability(&ordered) - p).abs() < 1e-12);
+    }
+
+    #[test]
+    fn every_atom_needs_a_probability() {
+        let mut interned = Interner::new();
+        let expr = parse("a & b", &mut interned);
+        let probs = utils::parse_assignments("a=0.5", &mut interned).unwrap();
+        assert!(probability(&expr, &probs, &interned).is_err());
+        assert!(utils::parse_assignments("a=1.5", &mut interned).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use crate::{parser::{self, Expr, Rule}, history::History, interner::Interner, runtime};
+
+// Splits "expr [RuleName]" into the expression and the rule name, the first line has no justification
+fn split_justification(line: &str) -> Result<(&str, Option<&str>), String> {
+    let line = line.trim();
+    if !line.ends_with(']') {
+        return Ok((line, None));
+    }
+    match line.rfind('[') {
+        Some(i) => Ok((&line[..i], Some(line[i + 1..line.len() - 1].trim()))),
+        None => Err("Missing '[' before the rule name".to_string()),
+    }
+}
+
+// A step is valid when one application of the rule, in either direction and at any sub-expression,
+// turns the previous expression into the next one
+fn follows(prev: &Expr, next: &Expr, lhs: &Expr, rhs: &Expr) -> bool {
+    let target = runtime::strip_groups(next);
+    runtime::rewrite_subterms(prev, lhs, rhs)
+        .into_iter()
+        .chain(runtime::rewrite_subterms(prev, rhs, lhs))
+        .any(|e| runtime::strip_groups(&e) == target)
+}
+
+// Returns the first and the last expression of a valid derivation, or the first invalid step with the reason
+pub fn check(lines: &[String], interned: &mut Interner, history: &History, rule_bindings: &HashMap<String, Rule>) -> Result<(Expr, Expr), String> {
+    let mut steps: Vec<Expr> = Vec::new();
+    for (i, line) in lines.iter().filter(|l| !l.trim().is_empty()).enumerate() {
+        let n = i + 1;
+        let (text, name) = split_justification(line).map_err(|msg| format!("step {}: {}", n, msg))?;
+        let expr = parser::parse_formula(text, interned, history).map_err(|msg| format!("step {}: {}", n, msg))?;
+        match (steps.last(), name) {
+            (None, None) => {},
+            (None, Some(_)) => return Err("step 1: the first expression has no justification".to_string()),
+            (Some(_), None) => return Err(format!("step {}: missing justification such as [RuleName]", n)),
+            (Some(prev), Some(name)) => {
+                let Some(Rule::Equivalence(lhs, rhs)) = rule_bindings.get(name) else {
+                    return Err(format!("step {}: '{}' is not a bound rule", n, name));
+                };
+                if !follows(prev, &expr, lhs, rhs) {
+                    return Err(format!("step {}: does not follow from step {} by one application of {}", n, n - 1, name));
+                }
+            },
+        }
+        steps.push(expr);
+    }
+    match (steps.first(), steps.last()) {
+        (Some(first), Some(last)) => Ok((first.clone(), last.clone())),
+        _ => Err("The derivation is empty".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stdlib, utils};
+
+    fn check_str(text: &str) -> Result<String, String> {
+        let mut interned = Interner::new();
+        let mut rule_bindings = HashMap::new();
+        stdlib::load(&mut rule_bindings);
+        let lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+        let (first, last) = check(&lines, &mut interned, &History::new(), &rule_bindings)?;
+        Ok(format!("{} = {}", utils::expr_to_string(&first, &interned), utils::expr_to_string(&last, &interned)))
+    }
+
+    #[test]
+    fn rules_apply_in_both_directions_and_inside() {
+        let text = "
+            ~(a & b) -> c
+            (~a | ~b) -> c   [std::demorganAnd]
+            ~(~a | ~b) | c   [std::implElim]
+            ~~(a & b) | c    [std::demorganAnd]
+            (a & b) | c      [std::doubleNeg]
+        ";
+        assert_eq!(check_str(text), Ok("~(a & b) -> c = (a & b) | c".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        assert_eq!(check_str("a & b\nb | a [std::commAnd]"), Err("step 2: does not follow from step 1 by one application of std::commAnd".to_string()));
+        assert_eq!(check_str("a & b\nb & a [comm]"), Err("step 2: 'comm' is not a bound rule".to_string()));
+        assert_eq!(check_str("a & b\nb & a"), Err("step 2: missing justification such as [RuleName]".to_string()));
+        assert_eq!(check_str("a [std::doubleNeg]"), Err("step 1: the first expression has no justification".to_string()));
+        assert_eq!(check_str("  "), Err("The derivation is empty".to_string()));
+    }
+
+    #[test]
+    fn one_step_at_a_time() {
+        assert!(check_str("~~~~a\n~~a [std::doubleNeg]\na [std::doubleNeg]").is_ok());
+        assert!(check_str("~~~~a\na [std::doubleNeg]").is_err());
+    }
+}
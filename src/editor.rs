@@ -0,0 +1,159 @@
+use std::{io::{self, IsTerminal, Write}, path::PathBuf};
+
+use rustyline::{
+    completion::{Completer, Pair}, config::Configurer, error::ReadlineError, highlight::Highlighter, hint::Hinter, 
+    history::FileHistory, validate::Validator, CompletionType, Config, Context, EditMode, Editor, Helper,
+};
+
+use crate::lexer;
+
+const COMMANDS: [&str; 32] = [
+    "help", "history", "atoms", "let", "load", "save", "restore", "std", "toggle", "logic", "tableau", "resolve", 
+    "sequent", "fitch", "tautology", "equivalent", "derivation", "end", "chain", "simplify", "minimize", "kmap", 
+    "sum", "prod", "basis", "complete", "tnorm", "eval", "surface", "count", "prob", "quit",
+];
+
+const HISTORY_FILE: &str = ".plogic_history";
+
+// Completes keywords and rule names anywhere, and commands at the start of the line
+pub struct PlogicHelper {
+    rule_names: Vec<String>,
+}
+
+impl Completer for PlogicHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl PlogicHelper {
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == ':'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return (pos, Vec::new());
+        }
+        // Commands and keywords are followed by a space, a rule name may be the end of the input
+        let commands = if line[..start].trim().is_empty() { &COMMANDS[..] } else { &[] };
+        let mut candidates = commands.iter()
+            .chain(lexer::KEYWORDS.iter())
+            .map(|s| Pair { display: s.to_string(), replacement: format!("{} ", s) })
+            .chain(self.rule_names.iter().map(|s| Pair { display: s.clone(), replacement: s.clone() }))
+            .filter(|p| p.display.starts_with(word))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.display.cmp(&b.display));
+        candidates.dedup_by(|a, b| a.display == b.display);
+        (start, candidates)
+    }
+}
+
+impl Hinter for PlogicHelper {
+    type Hint = String;
+}
+
+impl Highlighter for PlogicHelper {}
+
+impl Validator for PlogicHelper {}
+
+impl Helper for PlogicHelper {}
+
+// Reads the lines of the REPL, with line editing when stdin is a terminal and plain reads otherwise
+pub enum LineReader {
+    Editor(Box<Editor<PlogicHelper, FileHistory>>, Option<PathBuf>),
+    Plain,
+}
+
+impl LineReader {
+    pub fn new() -> Self {
+        if !io::stdin().is_terminal() {
+            return LineReader::Plain;
+        }
+        let config = Config::builder()
+            .edit_mode(EditMode::Emacs)
+            .completion_type(CompletionType::List)
+            .auto_add_history(true)
+            .build();
+        match Editor::with_config(config) {
+            Ok(mut editor) => {
+                editor.set_helper(Some(PlogicHelper { rule_names: Vec::new() }));
+                editor.set_max_history_size(1000).expect("History size is valid");
+                let path = std::env::home_dir().map(|home| home.join(HISTORY_FILE));
+                if let Some(path) = &path {
+                    // A missing history file is normal on the first start
+                    let _ = editor.load_history(path);
+                }
+                LineReader::Editor(Box::new(editor), path)
+            },
+            Err(_) => LineReader::Plain,
+        }
+    }
+
+    // Returns None at the end of the input, e.g. Ctrl-D
+    pub fn read_line(&mut self, prompt: &str, rule_names: Vec<String>) -> Option<String> {
+        match self {
+            LineReader::Editor(editor, _) => {
+                if let Some(helper) = editor.helper_mut() {
+                    helper.rule_names = rule_names;
+                }
+                match editor.readline(prompt) {
+                    Ok(line) => Some(line),
+                    // Ctrl-C discards the current line
+                    Err(ReadlineError::Interrupted) => Some(String::new()),
+                    Err(_) => None,
+                }
+            },
+            LineReader::Plain => {
+                let mut input = String::new();
+                print!("{}", prompt);
+                io::stdout().flush().expect("Failed to flush stdout");
+                match io::stdin().read_line(&mut input).expect("Failed to read line from stdin") {
+                    0 => None,
+                    _ => Some(input),
+                }
+            },
+        }
+    }
+
+    pub fn save_history(&mut self) {
+        if let LineReader::Editor(editor, Some(path)) = self {
+            if let Err(e) = editor.save_history(path) {
+                println!("Could not save the history to {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn complete(line: &str) -> (usize, Vec<String>) {
+        let helper = PlogicHelper { rule_names: vec!["std::commAnd".to_string(), "std::commOr".to_string()] };
+        let (start, candidates) = helper.candidates(line, line.len());
+        (start, candidates.into_iter().map(|p| p.replacement).collect())
+    }
+
+    #[test]
+    fn completes_commands_at_the_start() {
+        assert_eq!(complete("tau"), (0, vec!["tautology ".to_string()]));
+        assert_eq!(complete("p & tau"), (4, Vec::<String>::new()));
+    }
+
+    #[test]
+    fn completes_keywords_and_rule_names() {
+        assert_eq!(complete("p imp"), (2, vec!["implies ".to_string()]));
+        assert_eq!(complete("a & b => std::comm").1, vec!["std::commAnd", "std::commOr"]);
+    }
+
+    #[test]
+    fn completes_after_multibyte_characters() {
+        assert_eq!(complete("∀p"), (3, Vec::<String>::new()));
+        assert_eq!(complete("∃x. x ∧fo").0, "∃x. x ∧".len());
+    }
+}
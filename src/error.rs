@@ -0,0 +1,104 @@
+use std::fmt::Display;
+
+use crate::{history::AnswerRef, parser::Quantifier};
+
+// Byte offsets into the input line, `end` is exclusive
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Error {
+    // Lexer and parser
+    UnexpectedChar(char, Span),
+    Expected { expected: &'static str, found: String, span: Span },
+    UnknownAnswer(AnswerRef, Span),
+    NotAnAtom(String, Span),            // a rule bound to or applied by something else than a name
+    NotAFormula(String, Span),          // a statement where a single expression is expected
+    Captured(String, String, Span),     // a definition or answer with a free atom that a quantifier around it binds
+    // Pattern matching
+    UndefinedRule(String),
+    NoMatch,
+    UnboundQuantifier,
+    UnboundVariable,
+    Capture(Quantifier),
+    Escape,
+}
+
+impl Error {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::UnexpectedChar(_, span)
+            | Error::Expected { span, .. }
+            | Error::UnknownAnswer(_, span)
+            | Error::NotAnAtom(_, span)
+            | Error::NotAFormula(_, span)
+            | Error::Captured(_, _, span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    // The message, followed by the input with a caret under the offending part
+    pub fn render(&self, input: &str) -> String {
+        let Some(span) = self.span().filter(|span| span.start <= input.len()) else {
+            return self.to_string();
+        };
+        let end = span.end.clamp(span.start, input.len());
+        let column = input[..span.start].chars().count();
+        let width = input[span.start..end].chars().count().max(1);
+        format!("{}\n  {}\n  {}{}", self, input, " ".repeat(column), "^".repeat(width))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnexpectedChar(c, _) => write!(f, "Unexpected character '{}'", c),
+            Error::Expected { expected, found, .. } => write!(f, "Expected {}, found {}", expected, found),
+            Error::UnknownAnswer(answer, _) => write!(f, "Unknown answer reference: {} is not in the history", answer),
+            Error::NotAnAtom(found, _) => write!(f, "Expected the name of a rule, found {}", found),
+            Error::NotAFormula(found, _) => write!(f, "Expected a propositional expression, found {}", found),
+            Error::Captured(name, atom, _) => write!(f, "{} has the free atom {}, which the quantifier around it would capture", name, atom),
+            Error::UndefinedRule(name) => write!(f, "Undefined rule {}", name),
+            Error::NoMatch => write!(f, "Expression does not match either side of the rule"),
+            Error::UnboundQuantifier => write!(f, "Quantifier of the rule does not bind an atom of the expression"),
+            Error::UnboundVariable => write!(f, "Pattern could not be found in expression or rule"),
+            Error::Capture(q) => write!(f, "Substitution would capture the atom bound by the {} quantifier", q),
+            Error::Escape => write!(f, "Substitution would move a bound atom out of its quantifier"),
+        }
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caret_under_the_span() {
+        let err = Error::Expected { expected: "an expression", found: "')'".to_string(), span: Span { start: 4, end: 5 } };
+        assert_eq!(err.render("p & )"), "Expected an expression, found ')'\n  p & )\n      ^");
+    }
+
+    #[test]
+    fn caret_counts_characters_not_bytes() {
+        let err = Error::UnexpectedChar('#', Span { start: 8, end: 9 });
+        assert_eq!(err.render("∀p. p #"), "Unexpected character '#'\n  ∀p. p #\n        ^");
+    }
+
+    #[test]
+    fn empty_spans_and_errors_without_a_span() {
+        let err = Error::Expected { expected: "an expression", found: "end of input".to_string(), span: Span { start: 3, end: 3 } };
+        assert_eq!(err.render("p &"), "Expected an expression, found end of input\n  p &\n     ^");
+        assert_eq!(Error::NoMatch.render("p"), Error::NoMatch.to_string());
+        let outside = Error::NotAnAtom("x".to_string(), Span { start: 10, end: 12 });
+        assert_eq!(outside.render("p"), outside.to_string());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{parser::{self, Expr, BinOperator}, history::History, interner::Interner, runtime::{self, not}};
+
+const RULES: &[&str] = &[
+    "premise", "assumption", "reit", "&I", "&E", "|I", "|E", "->I", "->E", "<->I", "<->E",
+    "~I", "~E", "~~E", "0E", "1I", "RAA",
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Citation {
+    Line(usize),
+    Subproof(usize, usize),
+}
+
+struct Line {
+    formula: Expr,
+    rule: String,
+    citations: Vec<Citation>,
+    // Assumption lines of the subproofs that contain this line, outermost first
+    scope: Vec<usize>,
+}
+
+// A line reads "n. | | formula  rule citations", where every leading '|' is one level of subproof
+fn parse_line(text: &str, interned: &mut Interner) -> Result<(usize, usize, Expr, String, Vec<Citation>), String> {
+    let text = text.trim();
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+    let number = digits.parse::<usize>().map_err(|_| "Expected a line number".to_string())?;
+    let rest = text[digits.len()..].trim_start_matches('.').trim_start();
+    let depth = rest.chars().take_while(|c| *c == '|' || c.is_whitespace()).filter(|c| *c == '|').count();
+    let rest = rest.trim_start_matches(|c: char| c == '|' || c.is_whitespace());
+
+    let words = rest.split_whitespace().collect::<Vec<_>>();
+    let Some(k) = words.iter().rposition(|w| RULES.contains(w)) else {
+        return Err(format!("Expected one of the rules {}", RULES.join(", ")));
+    };
+    let formula = runtime::strip_groups(&parser::parse_formula(&words[..k].join(" "), interned, &History::new())?);
+    let citations = words[k + 1..]
+        .join("")
+        .split(',')
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            let parse = |n: &str| n.parse::<usize>().map_err(|_| format!("Expected a line number or a range, found '{}'", c));
+            match c.split_once('-') {
+                Some((a, b)) => Ok(Citation::Subproof(parse(a)?, parse(b)?)),
+                None => Ok(Citation::Line(parse(c)?)),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((number, depth, formula, words[k].to_string(), citations))
+}
+
+fn binary(l: &Expr, op: BinOperator, r: &Expr) -> Expr {
+    Expr::Binary(Box::new(l.clone()), op, Box::new(r.clone()))
+}
+
+struct Checker<'a> {
+    lines: &'a [Line],
+    current: usize,
+}
+
+impl Checker<'_> {
+    // A line can be cited while every subproof that contains it is still open
+    fn line(&self, citation: Citation) -> Result<&Expr, String> {
+        let Citation::Line(n) = citation else {
+            return Err("Expected a line, found a subproof".to_string());
+        };
+        if n == 0 || n > self.current {
+            return Err(format!("Line {} is not before this line", n));
+        }
+        let line = &self.lines[n - 1];
+        if !self.lines[self.current].scope.starts_with(&line.scope) {
+            return Err(format!("Line {} is inside a closed subproof", n));
+        }
+        Ok(&line.formula)
+    }
+
+    // A subproof a-b is cited by its assumption and its last line, once it is closed
+    fn subproof(&self, citation: Citation) -> Result<(&Expr, &Expr), String> {
+        let Citation::Subproof(a, b) = citation else {
+            return Err("Expected a subproof such as 3-5, found a line".to_string());
+        };
+        if a == 0 || a > b || b > self.current {
+            return Err(format!("Subproof {}-{} is not before this line", a, b));
+        }
+        let start = &self.lines[a - 1];
+        if start.rule != "assumption" {
+            return Err(format!("Line {} is not an assumption", a));
+        }
+        let outer = &start.scope[..start.scope.len() - 1];
+        let here = &self.lines[self.current].scope;
+        if here.contains(&a) || !here.starts_with(outer) {
+            return Err(format!("Subproof {}-{} is not closed or not accessible", a, b));
+        }
+        let last = (a..=self.current).take_while(|i| self.lines[i - 1].scope.contains(&a)).last().unwrap_or(a);
+        if last != b {
+            return Err(format!("The subproof starting at line {} ends at line {}", a, last));
+        }
+        Ok((&start.formula, &self.lines[b - 1].formula))
+    }
+
+    fn check(&self) -> Result<(), String> {
+        use BinOperator::*;
+        let line = &self.lines[self.current];
+        let f = &line.formula;
+        let c = &line.citations;
+        let falsum = Expr::Constant(false);
+        let expected = |n: usize| if c.len() == n { Ok(()) } else { Err(format!("{} expects {} citations", line.rule, n)) };
+
+        let valid = match line.rule.as_str() {
+            "premise" | "assumption" => { expected(0)?; true },
+            "reit" => { expected(1)?; self.line(c[0])? == f },
+            "1I" => { expected(0)?; *f == Expr::Constant(true) },
+            "&I" => {
+                expected(2)?;
+                let (a, b) = (self.line(c[0])?, self.line(c[1])?);
+                *f == binary(a, And, b) || *f == binary(b, And, a)
+            },
+            "&E" => {
+                expected(1)?;
+                matches!(self.line(c[0])?, Expr::Binary(l, And, r) if **l == *f || **r == *f)
+            },
+            "|I" => {
+                expected(1)?;
+                let a = self.line(c[0])?;
+                matches!(f, Expr::Binary(l, Or, r) if **l == *a || **r == *a)
+            },
+            "|E" => {
+                expected(3)?;
+                let Expr::Binary(l, Or, r) = self.line(c[0])? else {
+                    return Err("The first citation of |E is not a disjunction".to_string());
+                };
+                let (a1, b1) = self.subproof(c[1])?;
+                let (a2, b2) = self.subproof(c[2])?;
+                ((**l == *a1 && **r == *a2) || (**l == *a2 && **r == *a1)) && b1 == f && b2 == f
+            },
+            "->I" => {
+                expected(1)?;
+                let (a, b) = self.subproof(c[0])?;
+                *f == binary(a, Arrow, b)
+            },
+            "->E" => {
+                expected(2)?;
+                let (a, b) = (self.line(c[0])?, self.line(c[1])?);
+                *a == binary(b, Arrow, f) || *b == binary(a, Arrow, f)
+            },
+            "<->I" => {
+                expected(2)?;
+                let Expr::Binary(l, TwinArrow, r) = f else {
+                    return Err("<->I concludes a biconditional".to_string());
+                };
+                let (forward, backward) = (binary(l, Arrow, r), binary(r, Arrow, l));
+                match (c[0], c[1]) {
+                    (Citation::Line(_), Citation::Line(_)) => {
+                        let (a, b) = (self.line(c[0])?, self.line(c[1])?);
+                        (*a == forward && *b == backward) || (*a == backward && *b == forward)
+                    },
+                    _ => {
+                        let (a1, b1) = self.subproof(c[0])?;
+                        let (a2, b2) = self.subproof(c[1])?;
+                        let (s1, s2) = (binary(a1, Arrow, b1), binary(a2, Arrow, b2));
+                        (s1 == forward && s2 == backward) || (s1 == backward && s2 == forward)
+                    },
+                }
+            },
+            "<->E" => {
+                expected(2)?;
+                let (a, b) = (self.line(c[0])?, self.line(c[1])?);
+                *a == binary(b, TwinArrow, f) || *a == binary(f, TwinArrow, b)
+                    || *b == binary(a, TwinArrow, f) || *b == binary(f, TwinArrow, a)
+            },
+            "~I" => {
+                expected(1)?;
+                let (a, b) = self.subproof(c[0])?;
+                *b == falsum && *f == not(a)
+            },
+            "~E" => {
+                expected(2)?;
+                let (a, b) = (self.line(c[0])?, self.line(c[1])?);
+                *f == falsum && (*a == not(b) || *b == not(a))
+            },
+            "~~E" => {
+                expected(1)?;
+                *self.line(c[0])? == not(&not(f))
+            },
+            "0E" => {
+                expected(1)?;
+                *self.line(c[0])? == falsum
+            },
+            "RAA" => {
+                expected(1)?;
+                let (a, b) = self.subproof(c[0])?;
+                *b == falsum && *a == not(f)
+            },
+            other => return Err(format!("Unknown rule {}", other)),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("{} does not derive this formula from the cited lines", line.rule))
+        }
+    }
+}
+
+// Returns the premises and the conclusion of a valid proof, or the first invalid line with the reason
+pub fn check(text: &str, interned: &mut Interner) -> Result<(Vec<Expr>, Expr), String> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut scope: Vec<usize> = Vec::new();
+    for (i, text) in text.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let n = i + 1;
+        let (number, depth, formula, rule, citations) = parse_line(text, interned).map_err(|msg| format!("line {}: {}", n, msg))?;
+        if number != n {
+            return Err(format!("line {}: expected line number {}, found {}", n, n, number));
+        }
+        if rule == "premise" && (depth > 0 || lines.iter().any(|l| l.rule != "premise")) {
+            return Err(format!("line {}: premises come first, outside of subproofs", n));
+        }
+        let open = if rule == "assumption" { depth.checked_sub(1) } else { Some(depth) };
+        match open {
+            Some(d) if d <= scope.len() => scope.truncate(d),
+            _ => return Err(format!("line {}: a subproof at depth {} is not open here", n, depth)),
+        }
+        if rule == "assumption" {
+            scope.push(n);
+        }
+        lines.push(Line { formula, rule, citations, scope: scope.clone() });
+        Checker { lines: &lines, current: i }.check().map_err(|msg| format!("line {}: {}", n, msg))?;
+    }
+
+    match lines.last() {
+        None => Err("The proof is empty".to_string()),
+        Some(line) if !line.scope.is_empty() => Err(format!("line {}: the proof ends inside a subproof", lines.len())),
+        Some(line) => {
+            let premises = lines.iter().filter(|l| l.rule == "premise").map(|l| l.formula.clone()).collect();
+            Ok((premises, line.formula.clone()))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn proven(text: &str) -> Result<String, String> {
+        let mut interned = Interner::new();
+        let (premises, conclusion) = check(text, &mut interned)?;
+        Ok(utils::expr_to_string(&Expr::Argument(premises, Box::new(conclusion)), &interned))
+    }
+
+    #[test]
+    fn checks_a_proof_with_subproofs() {
+        let proof = "
+            1. p -> q        premise
+            2. q -> r        premise
+            3. | p           assumption
+            4. | q           ->E 1,3
+            5. | r           ->E 2,4
+            6. p -> r        ->I 3-5
+        ";
+        assert_eq!(proven(proof), Ok("p -> q, q -> r |- p -> r".to_string()));
+    }
+
+    #[test]
+    fn proof_without_premises() {
+        let proof = "
+            1. | p           assumption
+            2. p -> p        ->I 1-1
+        ";
+        assert_eq!(proven(proof), Ok("|- p -> p".to_string()));
+    }
+
+    #[test]
+    fn reductio_ad_absurdum() {
+        let proof = "
+            1. ~~p           premise
+            2. | ~p          assumption
+            3. | 0           ~E 1,2
+            4. p             RAA 2-3
+        ";
+        assert_eq!(proven(proof), Ok("~~p |- p".to_string()));
+    }
+
+    #[test]
+    fn rejects_lines_of_closed_subproofs() {
+        let proof = "
+            1. | p           assumption
+            2. p -> p        ->I 1-1
+            3. p             reit 1
+        ";
+        assert_eq!(proven(proof), Err("line 3: Line 1 is inside a closed subproof".to_string()));
+    }
+
+    #[test]
+    fn rejects_misplaced_premises_and_open_subproofs() {
+        assert!(proven("1. | p assumption\n2. q premise").unwrap_err().starts_with("line 2: premises come first"));
+        assert!(proven("1. | p assumption").unwrap_err().contains("ends inside a subproof"));
+        assert!(proven("1. p premise\n2. p & q &I 1,1").unwrap_err().contains("does not derive"));
+        assert_eq!(proven(""), Err("The proof is empty".to_string()));
+    }
+}
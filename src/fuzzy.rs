@@ -0,0 +1,186 @@
+use std::{collections::HashMap, fmt::Display};
+use crate::{parser::{Expr, BinOperator}, interner::Interner, runtime, utils};
+
+// Above this number of rows a surface table is no longer readable
+const MAX_SURFACE_ROWS: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TNorm {
+    Godel,
+    Product,
+    Lukasiewicz,
+}
+
+pub const TNORMS: &str = "godel, product, lukasiewicz";
+
+impl TNorm {
+    pub fn from_name(name: &str) -> Option<TNorm> {
+        match name.to_lowercase().as_str() {
+            "godel" | "gödel" | "min" => Some(TNorm::Godel),
+            "product" | "goguen" => Some(TNorm::Product),
+            "lukasiewicz" | "łukasiewicz" => Some(TNorm::Lukasiewicz),
+            _ => None,
+        }
+    }
+
+    pub fn and(&self, a: f64, b: f64) -> f64 {
+        match self {
+            TNorm::Godel => a.min(b),
+            TNorm::Product => a * b,
+            TNorm::Lukasiewicz => (a + b - 1.0).max(0.0),
+        }
+    }
+
+    // The dual t-conorm
+    pub fn or(&self, a: f64, b: f64) -> f64 {
+        match self {
+            TNorm::Godel => a.max(b),
+            TNorm::Product => a + b - a * b,
+            TNorm::Lukasiewicz => (a + b).min(1.0),
+        }
+    }
+
+    // The residuum, i.e. the largest c such that and(a, c) <= b
+    pub fn implies(&self, a: f64, b: f64) -> f64 {
+        if a <= b {
+            return 1.0;
+        }
+        match self {
+            TNorm::Godel => b,
+            TNorm::Product => b / a,
+            TNorm::Lukasiewicz => 1.0 - a + b,
+        }
+    }
+
+    // Negation is implication of falsity, for Gödel and product logic only 0 is mapped to 1
+    pub fn not(&self, a: f64) -> f64 {
+        self.implies(a, 0.0)
+    }
+}
+
+impl Display for TNorm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TNorm::Godel => write!(f, "Gödel"),
+            TNorm::Product => write!(f, "product"),
+            TNorm::Lukasiewicz => write!(f, "Łukasiewicz"),
+        }
+    }
+}
+
+pub fn eval(expr: &Expr, tnorm: TNorm, valuation: &HashMap<usize, f64>, interned: &Interner) -> Result<f64, String> {
+    match expr {
+        Expr::Binary(l, op, r) => {
+            let left = eval(l, tnorm, valuation, interned)?;
+            let right = eval(r, tnorm, valuation, interned)?;
+            Ok(match op {
+                BinOperator::And => tnorm.and(left, right),
+                BinOperator::Or => tnorm.or(left, right),
+                BinOperator::Arrow => tnorm.implies(left, right),
+                BinOperator::TwinArrow => tnorm.implies(left, right).min(tnorm.implies(right, left)),
+            })
+        },
+        Expr::Not(e) => Ok(tnorm.not(eval(e, tnorm, valuation, interned)?)),
+        Expr::Group(e) => eval(e, tnorm, valuation, interned),
+        Expr::Primary(n) => valuation
+            .get(n)
+            .copied()
+            .ok_or(format!("No value given for atom {}", interned[*n])),
+        Expr::Constant(b) => Ok(*b as usize as f64),
+        other => Err(format!("Cannot evaluate {} in fuzzy logic", utils::expr_to_string(other, interned))),
+    }
+}
+
+// Samples every atom at 0, 1/steps, .., 1, with atom j as digit j of the row number as in truth tables
+pub fn print_surface(expr: &Expr, tnorm: TNorm, steps: usize, interned: &Interner) -> Result<(), String> {
+    let mut atoms = runtime::free_atoms(expr);
+    atoms.sort();
+    let samples = steps + 1;
+    let rows = (0..atoms.len()).try_fold(1usize, |acc, _| acc.checked_mul(samples)).unwrap_or(usize::MAX);
+    if rows > MAX_SURFACE_ROWS {
+        return Err(format!("A surface of {} rows is too large, use fewer steps or atoms", rows));
+    }
+
+    let mut valuation = atoms.iter().map(|n| (*n, 0.0)).collect::<HashMap<_, _>>();
+    eval(expr, tnorm, &valuation, interned)?;
+
+    let mut headers = atoms.iter().rev().map(|n| interned[*n].to_string()).collect::<Vec<_>>();
+    headers.push(utils::expr_to_string(expr, interned));
+    let widths = headers.iter().map(|h| h.len().max(4)).collect::<Vec<_>>();
+    let line = |cells: &[String]| cells
+        .iter()
+        .zip(widths.iter())
+        .map(|(c, w)| format!("| {:^1$} |", c, w))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let header = headers.iter().zip(widths.iter()).map(|(h, w)| format!("[ {:^1$} ]", h, w)).collect::<Vec<_>>().join(" ");
+    println!("{:-<1$}", "", header.chars().count());
+    println!("{}", header);
+    println!("{}", widths.iter().map(|w| format!("|{:-<1$}|", "", w + 2)).collect::<Vec<_>>().join(" "));
+    for i in 0..rows {
+        for (j, n) in atoms.iter().enumerate() {
+            let digit = (i / samples.pow(j as u32)) % samples;
+            valuation.insert(*n, digit as f64 / steps as f64);
+        }
+        let mut cells = atoms.iter().rev().map(|n| format!("{:.2}", valuation[n])).collect::<Vec<_>>();
+        cells.push(format!("{:.2}", eval(expr, tnorm, &valuation, interned)?));
+        println!("{}", line(&cells));
+    }
+    println!("{:-<1$}", "", header.chars().count());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    const ALL: [TNorm; 3] = [TNorm::Godel, TNorm::Product, TNorm::Lukasiewicz];
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn eval_str(input: &str, tnorm: TNorm, values: &str) -> Result<f64, String> {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula(input, &mut interned, &History::new())?;
+        let valuation = utils::parse_assignments(values, &mut interned)?;
+        eval(&expr, tnorm, &valuation, &interned)
+    }
+
+    #[test]
+    fn t_norms_and_their_conorms() {
+        let results = ALL.map(|t| (t.and(0.6, 0.7), t.or(0.6, 0.7)));
+        assert!(close(results[0].0, 0.6) && close(results[0].1, 0.7));
+        assert!(close(results[1].0, 0.42) && close(results[1].1, 0.88));
+        assert!(close(results[2].0, 0.3) && close(results[2].1, 1.0));
+    }
+
+    #[test]
+    fn residuum_is_the_largest_implication() {
+        for t in ALL {
+            assert_eq!(t.implies(0.3, 0.5), 1.0);
+            let c = t.implies(0.8, 0.4);
+            assert!(t.and(0.8, c) <= 0.4 + 1e-9);
+            assert!(t.and(0.8, c + 0.01) > 0.4);
+        }
+        assert_eq!(TNorm::Godel.not(0.2), 0.0);
+        assert!(close(TNorm::Lukasiewicz.not(0.2), 0.8));
+    }
+
+    #[test]
+    fn evaluates_formulas() {
+        assert!(close(eval_str("p & ~q | 0", TNorm::Lukasiewicz, "p=0.9, q=0.3").unwrap(), 0.6));
+        assert!(close(eval_str("p <-> q", TNorm::Product, "p=0.5, q=0.25").unwrap(), 0.5));
+        assert!(eval_str("p & q", TNorm::Godel, "p=0.5").is_err());
+        assert!(eval_str("forall p. p", TNorm::Godel, "p=0.5").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(TNorm::from_name("Gödel"), Some(TNorm::Godel));
+        assert_eq!(TNorm::from_name("goguen"), Some(TNorm::Product));
+        assert_eq!(TNorm::from_name("drastic"), None);
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::Display;
+use crate::{parser::Expr, interner::Interner, utils};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum AnswerRef {
+    Last,                   // ans
+    Absolute(usize),        // $n
+    Relative(usize),        // ans[-n]
+}
+
+impl Display for AnswerRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerRef::Last => write!(f, "ans"),
+            AnswerRef::Absolute(n) => write!(f, "${}", n),
+            AnswerRef::Relative(n) => write!(f, "ans[-{}]", n),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<Expr>,
+    definitions: Vec<(String, Expr)>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), definitions: Vec::new() }
+    }
+
+    pub fn push(&mut self, expr: Expr) -> usize {
+        self.entries.push(expr);
+        self.entries.len()
+    }
+
+    pub fn get(&self, answer: AnswerRef) -> Option<&Expr> {
+        let index = match answer {
+            AnswerRef::Last => self.entries.len().checked_sub(1)?,
+            AnswerRef::Absolute(n) => n.checked_sub(1)?,
+            AnswerRef::Relative(n) => self.entries.len().checked_sub(n)?,
+        };
+        self.entries.get(index)
+    }
+
+    // Binds a name to an expression, replacing an earlier definition with the same name
+    pub fn define(&mut self, name: &str, expr: Expr) {
+        match self.definitions.iter_mut().find(|(n, _)| n == name) {
+            Some(def) => def.1 = expr,
+            None => self.definitions.push((name.to_string(), expr)),
+        }
+    }
+
+    pub fn definition(&self, name: &str) -> Option<&Expr> {
+        self.definitions.iter().find(|(n, _)| n == name).map(|(_, e)| e)
+    }
+
+    pub fn entries(&self) -> &[Expr] {
+        &self.entries
+    }
+
+    pub fn definitions(&self) -> &[(String, Expr)] {
+        &self.definitions
+    }
+
+    pub fn print(&self, interned: &Interner) {
+        for (name, expr) in &self.definitions {
+            println!("{} = {}", name, utils::expr_to_string(expr, interned));
+        }
+        if self.entries.is_empty() {
+            println!("No answers in history yet");
+            return;
+        }
+        for (i, expr) in self.entries.iter().enumerate() {
+            println!("${}: {}", i + 1, utils::expr_to_string(expr, interned));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(n: usize) -> History {
+        let mut history = History::new();
+        for i in 0..n {
+            assert_eq!(history.push(Expr::Primary(i)), i + 1);
+        }
+        history
+    }
+
+    #[test]
+    fn answers_by_number_and_by_distance() {
+        let history = history(3);
+        assert_eq!(history.get(AnswerRef::Last), Some(&Expr::Primary(2)));
+        assert_eq!(history.get(AnswerRef::Absolute(1)), Some(&Expr::Primary(0)));
+        assert_eq!(history.get(AnswerRef::Relative(1)), Some(&Expr::Primary(2)));
+        assert_eq!(history.get(AnswerRef::Relative(3)), Some(&Expr::Primary(0)));
+    }
+
+    #[test]
+    fn missing_answers() {
+        assert_eq!(history(0).get(AnswerRef::Last), None);
+        let history = history(2);
+        assert_eq!(history.get(AnswerRef::Absolute(0)), None);
+        assert_eq!(history.get(AnswerRef::Absolute(3)), None);
+        assert_eq!(history.get(AnswerRef::Relative(0)), None);
+        assert_eq!(history.get(AnswerRef::Relative(3)), None);
+    }
+}
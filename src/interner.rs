@@ -0,0 +1,58 @@
+use std::{collections::HashMap, ops::Index};
+
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    names: Vec<String>,
+    lookup: HashMap<String, usize>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self { 
+            names: Vec::new(),
+            lookup: HashMap::new(),
+        }
+    }
+
+    pub fn intern(&mut self, name: &str) -> usize {
+        if let Some(n) = self.lookup.get(name) {
+            return *n;
+        }
+        self.names.push(name.to_string());
+        self.lookup.insert(name.to_string(), self.names.len()-1);
+        self.names.len()-1
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+impl Index<usize> for Interner {
+    type Output = String;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.names[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_keep_their_first_index() {
+        let mut interned = Interner::new();
+        assert_eq!(interned.intern("q"), 0);
+        assert_eq!(interned.intern("p"), 1);
+        assert_eq!(interned.intern("q"), 0);
+        assert_eq!(interned.names(), ["q", "p"]);
+        assert_eq!(interned[1], "p");
+    }
+
+    #[test]
+    fn names_are_case_sensitive() {
+        let mut interned = Interner::new();
+        assert_ne!(interned.intern("p"), interned.intern("P"));
+    }
+}
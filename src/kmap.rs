@@ -0,0 +1,143 @@
+use crate::{parser::Expr, interner::Interner, minimize::{self, Implicant}, utils};
+
+fn gray_code(bits: usize) -> Vec<usize> {
+    (0..1 << bits).map(|i| i ^ (i >> 1)).collect()
+}
+
+fn label(code: usize, bits: usize) -> String {
+    (0..bits).rev().map(|b| if code & (1 << b) != 0 { '1' } else { '0' }).collect()
+}
+
+fn cell(row: usize, result: &[usize], dc: &[usize], groups: &[Implicant]) -> String {
+    let value = if dc.contains(&row) {
+        '-'
+    } else if result[row] == 1 {
+        '1'
+    } else {
+        '0'
+    };
+    let letters = groups
+        .iter()
+        .enumerate()
+        .filter(|(_, g)| g.covers(row))
+        .map(|(i, _)| char::from_u32('A' as u32 + i as u32).unwrap_or('?'))
+        .collect::<String>();
+    if letters.is_empty() {
+        value.to_string()
+    } else {
+        format!("{} {}", value, letters)
+    }
+}
+
+// Row variables are the leftmost columns of the truth table, maps of 5 and 6 variables
+// are drawn as two mirrored halves separated by a double line
+fn render(result: &[usize], dc: &[usize], atoms: &[Expr], interned: &Interner) -> Result<Vec<String>, String> {
+    let vars = atoms.len();
+    if !(2..=minimize::MAX_VARS).contains(&vars) {
+        return Err(format!("Karnaugh maps need between 2 and {} variables, found {}", minimize::MAX_VARS, vars));
+    }
+
+    let on = (0..result.len()).filter(|i| result[*i] == 1 && !dc.contains(i)).collect::<Vec<_>>();
+    let groups = minimize::minimal_cover(vars, &on, dc);
+
+    let names = atoms.iter().rev().map(|a| utils::expr_to_string(a, interned)).collect::<Vec<_>>();
+    let row_bits = vars / 2;
+    let col_bits = vars - row_bits;
+    let rows = gray_code(row_bits);
+    let cols = gray_code(col_bits);
+
+    let cells = rows
+        .iter()
+        .map(|r| cols.iter().map(|c| cell((r << col_bits) | c, result, dc, &groups)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(0).max(col_bits) + 2;
+
+    let corner = format!("{}\\{}", names[..row_bits].concat(), names[row_bits..].concat());
+    let margin = corner.len().max(row_bits);
+    let separator = |i: usize, s: &'static str| if col_bits == 3 && i == 4 { s.repeat(2) } else { s.to_string() };
+
+    let mut header = format!("{: >1$} ", corner, margin);
+    let mut line = format!("{:-<1$}", "", margin + 1);
+    for (i, c) in cols.iter().enumerate() {
+        header.push_str(&format!("{}{:^2$}", separator(i, "|"), label(*c, col_bits), width));
+        line.push_str(&format!("{}{:-<2$}", separator(i, "+"), "", width));
+    }
+    header.push('|');
+    line.push('+');
+
+    let mut lines = vec![header, line.clone()];
+    for (i, r) in rows.iter().enumerate() {
+        if row_bits == 3 && i == 4 {
+            lines.push(line.replace('-', "="));
+        }
+        let mut out = format!("{: >1$} ", label(*r, row_bits), margin);
+        for (j, c) in cells[i].iter().enumerate() {
+            out.push_str(&format!("{} {:<2$}", separator(j, "|"), c, width - 1));
+        }
+        out.push('|');
+        lines.push(out);
+    }
+    lines.push(line);
+
+    for (i, g) in groups.iter().enumerate() {
+        let letter = char::from_u32('A' as u32 + i as u32).unwrap_or('?');
+        lines.push(format!("{}: {}", letter, utils::expr_to_string(&minimize::implicant_to_expr(g, atoms), interned)));
+    }
+    Ok(lines)
+}
+
+pub fn print(result: &[usize], dc: &[usize], atoms: &[Expr], interned: &Interner) -> Result<(), String> {
+    for line in render(result, dc, atoms, interned)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser, runtime::Table};
+
+    fn kmap(input: &str, dc: &[usize]) -> Result<Vec<String>, String> {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula(input, &mut interned, &History::new())?;
+        let mut table = Table::new(&interned, &true);
+        table.generate_truthtable(expr).unwrap();
+        render(table.result(), dc, table.atoms(), &interned)
+    }
+
+    #[test]
+    fn neighbouring_cells_differ_in_one_bit() {
+        let codes = gray_code(3);
+        assert_eq!(codes, vec![0, 1, 3, 2, 6, 7, 5, 4]);
+        assert_eq!(label(6, 3), "110");
+    }
+
+    #[test]
+    fn map_with_groups_and_dont_cares() {
+        let lines = kmap("a & b | c", &[0]).unwrap();
+        assert_eq!(lines, vec![
+            "c\\ba |  00  |  01  |  11  |  10  |",
+            "-----+------+------+------+------+",
+            "   0 | -    | 0    | 1 A  | 0    |",
+            "   1 | 1 B  | 1 B  | 1 AB | 1 B  |",
+            "-----+------+------+------+------+",
+            "A: b & a",
+            "B: c",
+        ]);
+    }
+
+    #[test]
+    fn large_maps_are_mirrored_halves() {
+        let lines = kmap("a & b & c & d & e & f", &[]).unwrap();
+        assert!(lines[0].contains("|| 110"));
+        assert!(lines.iter().any(|l| l.starts_with("=====")));
+        assert_eq!(lines.last().unwrap(), "A: f & e & d & c & b & a");
+    }
+
+    #[test]
+    fn needs_two_to_six_variables() {
+        assert!(kmap("a", &[]).is_err());
+        assert!(kmap("a & b & c & d & e & f & g", &[]).is_err());
+    }
+}
@@ -1,9 +1,11 @@
-use std::{iter::Peekable, str::Chars};
+use std::{fmt::Display, iter::Peekable, str::CharIndices};
+
+use crate::{history::AnswerRef, error::{Error, Span}};
 
     
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub enum Token {
-    Identifier(String),     // [a-z/A-Z] 
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum TokenKind {
+    Identifier(String),     // [a-z/A-Z] | namespace::name
     Not,                    // ~
     And,                    // &
     Or,                     // |
@@ -14,107 +16,235 @@ pub enum Token {
     OpenParen,              // (
     CloseParen,             // )
     Equal,                  // =
+    Answer(AnswerRef),      // ans | ans[-n] | $n
+    Constant(bool),         // 1 | 0
+    Forall,                 // forall | ∀
+    Exists,                 // exists | ∃
+    Dot,                    // .
+    Comma,                  // ,
+    Turnstile,              // |- | therefore
+    Eof,                    // end of input
 }
 
-fn add_token(list: &mut Vec<Token>, input: &mut Peekable<Chars>, token: Token) {
-    list.push(token);
-    input.next();
-}
-
-fn add_arrow(list: &mut Vec<Token>, input: &mut Peekable<Chars>){
-    input.next();
-    match input.peek() {
-        Some('>') => {
-            input.next();
-            list.push(Token::Arrow);
-        },
-        Some(other) => {
-            println!("Unexpected character: {}", other);
-            input.next();
-        },
-        None => {
-            println!("Unexpected character: None");
-            input.next();
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Identifier(s) => write!(f, "'{}'", s),
+            TokenKind::Not => write!(f, "'~'"),
+            TokenKind::And => write!(f, "'&'"),
+            TokenKind::Or => write!(f, "'|'"),
+            TokenKind::Arrow => write!(f, "'->'"),
+            TokenKind::TwinArrow => write!(f, "'<->'"),
+            TokenKind::Rule => write!(f, "'=>'"),
+            TokenKind::Binding => write!(f, "':='"),
+            TokenKind::OpenParen => write!(f, "'('"),
+            TokenKind::CloseParen => write!(f, "')'"),
+            TokenKind::Equal => write!(f, "'='"),
+            TokenKind::Answer(answer) => write!(f, "'{}'", answer),
+            TokenKind::Constant(true) => write!(f, "'1'"),
+            TokenKind::Constant(false) => write!(f, "'0'"),
+            TokenKind::Forall => write!(f, "'forall'"),
+            TokenKind::Exists => write!(f, "'exists'"),
+            TokenKind::Dot => write!(f, "'.'"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Turnstile => write!(f, "'|-'"),
+            TokenKind::Eof => write!(f, "end of input"),
         }
     }
 }
 
-fn add_twin_arrow(list: &mut Vec<Token>, input: &mut Peekable<Chars>){
-    
-    for c in "<->".chars(){
-        if c != *input.peek().unwrap() {
-            println!("Unexpected character: {}", c);
-            input.next();
-            return;
-        }
-        input.next();
-    }
-    list.push(Token::TwinArrow);
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+// Byte offset of the next character
+fn offset(input: &mut Chars, source: &str) -> usize {
+    input.peek().map_or(source.len(), |(i, _)| *i)
+}
+
+// An error at the next character, or at the end of the input
+fn expected(input: &mut Chars, source: &str, expected: &'static str) -> Error {
+    let start = offset(input, source);
+    let (found, end) = match input.peek() {
+        Some((i, c)) => (format!("'{}'", c), i + c.len_utf8()),
+        None => ("end of input".to_string(), start),
+    };
+    Error::Expected { expected, found, span: Span { start, end } }
+}
+
+fn next_is(input: &mut Chars, c: char) -> bool {
+    input.next_if(|(_, next)| *next == c).is_some()
 }
 
-fn identifier(list: &mut Vec<Token>, input: &mut Peekable<Chars>, prev_input: &mut String){
+fn number(input: &mut Chars) -> Option<usize> {
     let mut lexeme = String::new();
-    while let Some(c @ 'a'..='z') | Some(c @ 'A'..='Z') = input.peek() {
+    while let Some((_, c @ '0'..='9')) = input.peek() {
         lexeme.push(*c);
         input.next();
     }
+    lexeme.parse().ok()
+}
+
+fn answer_index(input: &mut Chars, source: &str) -> Result<TokenKind, Error> {
+    input.next();
+    let relative = next_is(input, '-');
+    let Some(n) = number(input) else {
+        return Err(expected(input, source, "a number in 'ans[-n]'"));
+    };
+    if !next_is(input, ']') {
+        return Err(expected(input, source, "']' after 'ans[-n'"));
+    }
+    if relative {
+        Ok(TokenKind::Answer(AnswerRef::Relative(n)))
+    } else {
+        Ok(TokenKind::Answer(AnswerRef::Absolute(n)))
+    }
+}
+
+fn identifier(first: char, input: &mut Chars, source: &str) -> Result<TokenKind, Error> {
+    let mut lexeme = first.to_string();
+    loop {
+        while let Some((_, c @ ('a'..='z' | 'A'..='Z'))) = input.peek() {
+            lexeme.push(*c);
+            input.next();
+        }
+        // Namespaced names such as std::demorganAnd
+        let mut ahead = input.clone();
+        if let (Some((_, ':')), Some((_, ':')), Some((_, 'a'..='z' | 'A'..='Z'))) = (ahead.next(), ahead.next(), ahead.next()) {
+            lexeme.push_str("::");
+            input.next();
+            input.next();
+        } else {
+            break;
+        }
+    }
     
-    match lexeme.as_str() {
+    let kind = match lexeme.as_str() {
         "ans" => {
-            if prev_input.len() > 0 {
-                tokenize(list, prev_input, &mut "".to_string());
-                return;
+            if let Some((_, '[')) = input.peek() {
+                answer_index(input, source)?
+            } else {
+                TokenKind::Answer(AnswerRef::Last)
             }
         },
-        "and" => list.push(Token::And),
-        "or" =>  list.push(Token::Or),
-        "not" => list.push(Token::Not),
-        "implies" => list.push(Token::Arrow),
-        "equiv" => list.push(Token::TwinArrow),
-        "rule" => list.push(Token::Rule),
-        _ => list.push(Token::Identifier(lexeme)),
-    }
+        "and" => TokenKind::And,
+        "or" => TokenKind::Or,
+        "not" => TokenKind::Not,
+        "implies" => TokenKind::Arrow,
+        "equiv" => TokenKind::TwinArrow,
+        "rule" => TokenKind::Rule,
+        "true" => TokenKind::Constant(true),
+        "false" => TokenKind::Constant(false),
+        "forall" => TokenKind::Forall,
+        "exists" => TokenKind::Exists,
+        "therefore" => TokenKind::Turnstile,
+        _ => TokenKind::Identifier(lexeme),
+    };
+    Ok(kind)
 }
 
-pub fn tokenize(list: &mut Vec<Token>, input: &mut String, prev_input: &mut String) {
-    let mut input = input.chars().peekable();
-    loop {
-        match input.peek() {
-            Some('&') => add_token(list, &mut input, Token::And),
-            Some('|') => add_token(list, &mut input, Token::Or),
-            Some('~') => add_token(list, &mut input, Token::Not),
-            Some('(') => add_token(list, &mut input, Token::OpenParen),
-            Some(')') => add_token(list, &mut input, Token::CloseParen),
-            Some('=') => {
-                input.next();
-                if let Some('>') = input.peek() {
-                    list.push(Token::Rule);
-                    input.next();
-                } else {
-                    list.push(Token::Equal);
-                }
-            },
-            Some(':') => {
-                input.next();
-                match input.next() {
-                    Some('=') => list.push(Token::Binding),
-                    other => println!("Unexpected character: expected '=', but found {:?}", other)
-                }
-            },
-            Some('<') => add_twin_arrow(list, &mut input),
-            Some('-') => add_arrow(list, &mut input),
-            Some('a'..='z') | Some('A'..='Z') => identifier(list, &mut input, prev_input),
+// The words that identifier() turns into tokens instead of atoms
+pub const KEYWORDS: [&str; 12] = ["ans", "and", "or", "not", "implies", "equiv", "rule", "true", "false", "forall", "exists", "therefore"];
 
-            Some(' ') | Some('\t') | Some('\n') => {
-                input.next();
-            },
-            
-            Some(other) => {
-                println!("Unexpected character: {}", other);
-                input.next();
+// Stops at the first character that does not start a token, the list always ends with an Eof token
+pub fn tokenize(list: &mut Vec<Token>, source: &str) -> Result<(), Error> {
+    let mut input = source.char_indices().peekable();
+    while let Some((start, c)) = input.next() {
+        let kind = match c {
+            '&' => TokenKind::And,
+            '|' if next_is(&mut input, '-') => TokenKind::Turnstile,
+            '|' => TokenKind::Or,
+            '~' => TokenKind::Not,
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '1' => TokenKind::Constant(true),
+            '0' => TokenKind::Constant(false),
+            '.' => TokenKind::Dot,
+            ',' => TokenKind::Comma,
+            '∀' => TokenKind::Forall,
+            '∃' => TokenKind::Exists,
+            '=' if next_is(&mut input, '>') => TokenKind::Rule,
+            '=' => TokenKind::Equal,
+            ':' if next_is(&mut input, '=') => TokenKind::Binding,
+            ':' => return Err(expected(&mut input, source, "'=' after ':'")),
+            '<' if next_is(&mut input, '-') && next_is(&mut input, '>') => TokenKind::TwinArrow,
+            '<' => return Err(expected(&mut input, source, "'<->'")),
+            '-' if next_is(&mut input, '>') => TokenKind::Arrow,
+            '-' => return Err(expected(&mut input, source, "'>' after '-'")),
+            '$' => match number(&mut input) {
+                Some(n) => TokenKind::Answer(AnswerRef::Absolute(n)),
+                None => return Err(expected(&mut input, source, "an answer number after '$'")),
             },
-            None => break,
-        }
+            'a'..='z' | 'A'..='Z' => identifier(c, &mut input, source)?,
+            ' ' | '\t' | '\n' => continue,
+            other => return Err(Error::UnexpectedChar(other, Span { start, end: start + other.len_utf8() })),
+        };
+        list.push(Token { kind, span: Span { start, end: offset(&mut input, source) } });
+    }
+    list.push(Token { kind: TokenKind::Eof, span: Span { start: source.len(), end: source.len() } });
+    Ok(())
+}
+
+// The name if the input is a single atom, e.g. the name in "let name = .."
+pub fn atom_name(input: &str) -> Option<String> {
+    let mut tokens = Vec::new();
+    tokenize(&mut tokens, input).ok()?;
+    match tokens.as_slice() {
+        [Token { kind: TokenKind::Identifier(name), .. }, Token { kind: TokenKind::Eof, .. }] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Result<Vec<TokenKind>, Error> {
+        let mut tokens = Vec::new();
+        tokenize(&mut tokens, input)?;
+        Ok(tokens.into_iter().map(|t| t.kind).collect())
     }
 
+    #[test]
+    fn answer_references() {
+        assert_eq!(kinds("ans & $12 | ans[-2]"), Ok(vec![
+            TokenKind::Answer(AnswerRef::Last),
+            TokenKind::And,
+            TokenKind::Answer(AnswerRef::Absolute(12)),
+            TokenKind::Or,
+            TokenKind::Answer(AnswerRef::Relative(2)),
+            TokenKind::Eof,
+        ]));
+        assert_eq!(kinds("ans[3]"), Ok(vec![TokenKind::Answer(AnswerRef::Absolute(3)), TokenKind::Eof]));
+        assert!(kinds("$").is_err());
+        assert!(kinds("ans[-]").is_err());
+        assert!(kinds("ans[-1").is_err());
+    }
+
+    #[test]
+    fn tokens_carry_byte_spans() {
+        let mut tokens = Vec::new();
+        tokenize(&mut tokens, "∀p. p <-> std::q").unwrap();
+        let spans = tokens.iter().map(|t| (t.span.start, t.span.end)).collect::<Vec<_>>();
+        assert_eq!(spans, vec![(0, 3), (3, 4), (4, 5), (6, 7), (8, 11), (12, 18), (18, 18)]);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_character() {
+        assert_eq!(kinds("p # q"), Err(Error::UnexpectedChar('#', Span { start: 2, end: 3 })));
+        assert_eq!(kinds("p -"), Err(Error::Expected { expected: "'>' after '-'", found: "end of input".to_string(), span: Span { start: 3, end: 3 } }));
+        assert_eq!(kinds("p <= q"), Err(Error::Expected { expected: "'<->'", found: "'='".to_string(), span: Span { start: 3, end: 4 } }));
+    }
+
+    #[test]
+    fn atom_names() {
+        assert_eq!(atom_name(" pq "), Some("pq".to_string()));
+        assert_eq!(atom_name("and"), None);
+        assert_eq!(atom_name("p q"), None);
+        assert_eq!(atom_name(""), None);
+    }
 }
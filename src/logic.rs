@@ -0,0 +1,131 @@
+use std::fmt::Display;
+use crate::parser::{BinOperator, Quantifier};
+
+// Truth values are 0 (false) up to `top()` (true), three-valued logics use 1 for unknown
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Logic {
+    Classical,
+    Kleene,
+    Lukasiewicz,
+}
+
+pub const LOGICS: &str = "classical, k3, l3";
+
+impl Logic {
+    pub fn from_name(name: &str) -> Option<Logic> {
+        match name.to_lowercase().as_str() {
+            "classical" | "c" => Some(Logic::Classical),
+            "k3" | "kleene" => Some(Logic::Kleene),
+            "l3" | "ł3" | "lukasiewicz" => Some(Logic::Lukasiewicz),
+            _ => None,
+        }
+    }
+
+    pub fn values(&self) -> usize {
+        match self {
+            Logic::Classical => 2,
+            Logic::Kleene | Logic::Lukasiewicz => 3,
+        }
+    }
+
+    pub fn top(&self) -> usize {
+        self.values() - 1
+    }
+
+    pub fn constant(&self, b: bool) -> usize {
+        if b { self.top() } else { 0 }
+    }
+
+    pub fn not(&self, a: usize) -> usize {
+        self.top() - a
+    }
+
+    pub fn binary(&self, op: BinOperator, a: usize, b: usize) -> usize {
+        let top = self.top();
+        match (op, self) {
+            (BinOperator::And, _) => a.min(b),
+            (BinOperator::Or, _) => a.max(b),
+            (BinOperator::Arrow, Logic::Kleene) => self.not(a).max(b),
+            (BinOperator::TwinArrow, Logic::Kleene) => self.not(a).max(b).min(self.not(b).max(a)),
+            // Classical logic is the two-valued case of Łukasiewicz logic
+            (BinOperator::Arrow, _) => top.min(top - a + b),
+            (BinOperator::TwinArrow, _) => top - a.abs_diff(b),
+        }
+    }
+
+    pub fn quantify(&self, q: Quantifier, values: &[usize]) -> usize {
+        match q {
+            Quantifier::Forall => values.iter().copied().min().unwrap_or(self.top()),
+            Quantifier::Exists => values.iter().copied().max().unwrap_or(0),
+        }
+    }
+
+    pub fn symbol(&self, v: usize) -> char {
+        match (self.values(), v) {
+            (_, 0) => 'F',
+            (3, 1) => 'U',
+            _ => 'T',
+        }
+    }
+}
+
+impl Display for Logic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Logic::Classical => write!(f, "classical"),
+            Logic::Kleene => write!(f, "K3"),
+            Logic::Lukasiewicz => write!(f, "Ł3"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, interner::Interner, parser, runtime};
+
+    fn table(logic: Logic, op: BinOperator) -> Vec<usize> {
+        (0..9).map(|i| logic.binary(op, i / 3, i % 3)).collect()
+    }
+
+    fn is_tautology(input: &str, logic: Logic) -> bool {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula(input, &mut interned, &History::new()).unwrap();
+        let mut table = runtime::Table::with_logic(&interned, &true, logic);
+        table.generate_truthtable(expr).unwrap();
+        table.is_tautology()
+    }
+
+    #[test]
+    fn kleene_and_lukasiewicz_differ_on_unknown_implications() {
+        assert_eq!(table(Logic::Kleene, BinOperator::Arrow), vec![2, 2, 2, 1, 1, 2, 0, 1, 2]);
+        assert_eq!(table(Logic::Lukasiewicz, BinOperator::Arrow), vec![2, 2, 2, 1, 2, 2, 0, 1, 2]);
+        assert_eq!(table(Logic::Kleene, BinOperator::And), table(Logic::Lukasiewicz, BinOperator::And));
+        assert_eq!(Logic::Kleene.not(1), 1);
+    }
+
+    #[test]
+    fn classical_logic_is_the_two_valued_case() {
+        let values = |op| (0..4).map(|i| Logic::Classical.binary(op, i / 2, i % 2)).collect::<Vec<_>>();
+        assert_eq!(values(BinOperator::Arrow), vec![1, 1, 0, 1]);
+        assert_eq!(values(BinOperator::TwinArrow), vec![1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn tautologies_depend_on_the_logic() {
+        assert!(is_tautology("p | ~p", Logic::Classical));
+        assert!(!is_tautology("p | ~p", Logic::Kleene));
+        assert!(!is_tautology("p -> p", Logic::Kleene));
+        assert!(is_tautology("p -> p", Logic::Lukasiewicz));
+        assert!(!is_tautology("p | ~p", Logic::Lukasiewicz));
+    }
+
+    #[test]
+    fn names_and_symbols() {
+        assert_eq!(Logic::from_name("K3"), Some(Logic::Kleene));
+        assert_eq!(Logic::from_name("ł3"), Some(Logic::Lukasiewicz));
+        assert_eq!(Logic::from_name("fuzzy"), None);
+        assert_eq!((0..3).map(|v| Logic::Kleene.symbol(v)).collect::<String>(), "FUT");
+        assert_eq!(Logic::Kleene.quantify(Quantifier::Forall, &[2, 1]), 1);
+    }
+}
@@ -1,83 +1,909 @@
-use std::{io::{Write, self}, collections::HashMap};
+use std::{io::{IsTerminal, self}, collections::HashMap};
 
-use crate::{parser::{Expr, Rule}, lexer::Token};
+use crate::{parser::{Expr, Rule}, history::History, interner::Interner, logic::Logic, fuzzy::TNorm};
 
+mod history;
+mod interner;
 mod lexer;
 mod parser;
 mod utils;
 mod runtime;
+mod simplify;
+mod minimize;
+mod kmap;
+mod synth;
+mod basis;
+mod bdd;
+mod counting;
+mod logic;
+mod fuzzy;
+mod tableau;
+mod resolution;
+mod sequent;
+mod fitch;
+mod argument;
+mod derivation;
+mod chain;
+mod stdlib;
+mod error;
+mod editor;
 
-fn main() {
-    let mut input = String::new();
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut prev_input: String = String::new();
-    let mut rule_bindings: HashMap<String, Rule> = HashMap::new();
-    let mut is_num_symbol: bool = true;
+// An argument "p, q |- r", or a single expression that is read as an argument without premises
+fn parse_argument(input: &str, interned: &mut Interner, history: &History) -> Result<(Vec<Expr>, Expr), String> {
+    match parser::parse_line(input, interned, history)? {
+        Expr::Argument(premises, conclusion) => Ok((premises, *conclusion)),
+        e @ (Expr::Pattern(..) | Expr::Binding(..)) => 
+            Err(format!("Expected an argument, found {}", utils::expr_to_string(&e, interned))),
+        e => Ok((Vec::new(), e)),
+    }
+}
 
-    println!("Welcome to the REPL of Plogic.");
-    utils::usage();
-    loop {
-        input.clear();
-        tokens.clear();
+fn split_dont_cares(input: &str) -> (&str, Result<Vec<usize>, String>) {
+    match input.split_once(" dc ") {
+        Some((rest, dc)) => {
+            let rows = dc
+                .split(',')
+                .map(|i| i.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "Expected a comma separated list of row numbers after 'dc'".to_string());
+            (rest, rows)
+        },
+        None => (input, Ok(Vec::new())),
+    }
+}
 
-        print!("> ");
-        io::stdout().flush().expect("Failed to flush stdout");
-        io::stdin().read_line(&mut input).expect("Failed to read line from stdin");
-        
-        let mut input = input.trim().to_string();
-        match input.as_str() {
+// Rule names share the symbol table with the atoms, but are not atoms themselves
+fn atom_names<'a>(interned: &'a Interner, rule_bindings: &HashMap<String, Rule>) -> Vec<&'a str> {
+    interned.names()
+        .iter()
+        .filter(|name| !rule_bindings.contains_key(*name) && !name.starts_with(stdlib::NAMESPACE))
+        .map(String::as_str)
+        .collect()
+}
+
+struct Session {
+    history: History,
+    interned: Interner,
+    rule_bindings: HashMap<String, Rule>,
+    is_num_symbol: bool,
+    logic: Logic,
+    tnorm: TNorm,
+    derivation: Option<Vec<String>>,
+    loading: Vec<String>,
+    // Set when a check fails, e.g. an invalid argument, for the exit code of scripts
+    failed: bool,
+}
+
+impl Session {
+    fn new() -> Self {
+        Self {
+            history: History::new(),
+            interned: Interner::new(),
+            rule_bindings: HashMap::new(),
+            is_num_symbol: true,
+            logic: Logic::Classical,
+            tnorm: TNorm::Godel,
+            derivation: None,
+            loading: Vec::new(),
+            failed: false,
+        }
+    }
+
+    fn load(&mut self, path: &str) -> Result<(), String> {
+        if self.loading.iter().any(|p| p == path) {
+            return Err(format!("{} is already being loaded", path));
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        self.loading.push(path.to_string());
+        let res = self.run(path, &text);
+        self.loading.pop();
+        res
+    }
+
+    // Runs every line of a script as if it was typed in the REPL, '#' starts a comment.
+    // Errors are printed as they happen, next to the output of the line that caused them
+    fn run(&mut self, path: &str, text: &str) -> Result<(), String> {
+        let mut errors = 0;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line == "quit" && self.derivation.is_none() {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if let Err(msg) = self.execute(line) {
+                eprintln!("{}:{}: {}", path, i + 1, msg);
+                errors += 1;
+            }
+        }
+        if self.derivation.take().is_some() {
+            eprintln!("{}: derivation is missing 'end'", path);
+            errors += 1;
+        }
+        if errors == 0 { Ok(()) } else { Err(format!("{}: {} error(s)", path, errors)) }
+    }
+
+    // Writes the session as a .plg script that restores it in a fresh session.
+    // Answers and rules are written before the definitions, so that reloading them does not expand defined names
+    fn save(&self, path: &str) -> Result<(), String> {
+        let mut lines = vec!["# plogic session".to_string()];
+        if !self.is_num_symbol {
+            lines.push("toggle".to_string());
+        }
+        lines.push(format!("logic {}", self.logic));
+        lines.push(format!("tnorm {}", self.tnorm));
+        let atoms = atom_names(&self.interned, &self.rule_bindings);
+        if !atoms.is_empty() {
+            lines.push(format!("atoms {}", atoms.join(", ")));
+        }
+        // The standard laws are loaded again by name, their pattern variables are not in the session interner
+        if !stdlib::names(&self.rule_bindings).is_empty() {
+            lines.push("std on".to_string());
+        }
+        let mut names = self.rule_bindings.keys().filter(|name| !name.starts_with(stdlib::NAMESPACE)).collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            lines.push(format!("{} := {}", name, utils::rule_to_string(&self.rule_bindings[name], &self.interned)));
+        }
+        for expr in self.history.entries() {
+            lines.push(format!("history {}", utils::expr_to_string(expr, &self.interned)));
+        }
+        for (name, expr) in self.history.definitions() {
+            lines.push(format!("let {} = {}", name, utils::expr_to_string(expr, &self.interned)));
+        }
+        lines.push(String::new());
+        std::fs::write(path, lines.join("\n")).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    fn execute(&mut self, input: &str) -> Result<(), String> {
+        if let (None, Some(path)) = (&self.derivation, input.strip_prefix("load ")) {
+            self.load(path.trim())?;
+            println!("Loaded {}", path.trim());
+            return Ok(());
+        }
+
+        if let (None, Some(path)) = (&self.derivation, input.strip_prefix("save ")) {
+            self.save(path.trim())?;
+            println!("Saved the session to {}", path.trim());
+            return Ok(());
+        }
+
+        // The current session is only replaced when the whole file restores without errors
+        if let (None, Some(path)) = (&self.derivation, input.strip_prefix("restore ")) {
+            let mut session = Session::new();
+            session.load(path.trim())?;
+            *self = session;
+            println!("Restored the session from {}", path.trim());
+            return Ok(());
+        }
+
+        let Session { history, interned, rule_bindings, is_num_symbol, logic, tnorm, derivation, failed, .. } = self;
+
+        if let Some(lines) = derivation {
+            if input == "end" {
+                let res = derivation::check(lines, interned, history, rule_bindings);
+                *derivation = None;
+                let (first, last) = res.map_err(|msg| format!("invalid derivation, {}", msg))?;
+                println!("valid derivation: {} = {}", 
+                    utils::expr_to_string(&first, interned), 
+                    utils::expr_to_string(&last, interned));
+            } else {
+                lines.push(input.to_string());
+            }
+            return Ok(());
+        }
+
+        match input {
             "help" => {
                 utils::usage();
-                continue;
+                return Ok(());
+            },
+            "history" => {
+                history.print(interned);
+                return Ok(());
             },
             "toggle" => {
-                is_num_symbol = !is_num_symbol;
-                if is_num_symbol {
+                *is_num_symbol = !*is_num_symbol;
+                if *is_num_symbol {
                     println!("Changed truthtable symbols from 'T'/'F' to '1'/'0'");
-                    continue;
                 } else {
                     println!("Changed truthtable symbols from '1'/'0' to 'T'/'F'");
-                    continue;
                 }
+                return Ok(());
+            },
+            "derivation" => {
+                *derivation = Some(Vec::new());
+                return Ok(());
             },
-            "\n" | "" => continue,
-            "quit" => break,
+            "end" => return Err("'end' outside of a derivation".to_string()),
+            "\n" | "" => return Ok(()),
             _ => {},
         }
 
-        lexer::tokenize(&mut tokens, &mut input, &mut prev_input);
-        
-        let mut interned: Vec<String> = Vec::new();
-        let expr = parser::parse(&mut tokens, &mut interned);
+        if let Some(rest) = input.strip_prefix("history ") {
+            let e = parser::parse_formula(rest, interned, history)?;
+            let n = history.push(e.clone());
+            println!("${}: {}", n, utils::expr_to_string(&e, interned));
+            return Ok(());
+        }
 
-        match expr {
-            Ok(parser::Expr::Pattern(e, rule)) => {
-                prev_input = match runtime::match_patterns(&*e, *rule, &interned, &rule_bindings) {
-                    Ok(s) => {
-                        println!("{}", s);
-                        s
-                    },
-                    Err(msg) => {
+        if let Some(rest) = input.strip_prefix("atoms ").or((input == "atoms").then_some("")) {
+            for name in rest.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                let Some(atom) = lexer::atom_name(name) else {
+                    return Err(format!("Expected an atom, found '{}'", name));
+                };
+                interned.intern(&atom);
+            }
+            println!("atoms: {}", atom_names(interned, rule_bindings).join(", "));
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("let ") {
+            let Some((name, rest)) = rest.split_once('=') else {
+                return Err("Expected 'let <name> = <expr>'".to_string());
+            };
+            let Some(name) = lexer::atom_name(name) else {
+                return Err(format!("Expected a name to define, found '{}'", name.trim()));
+            };
+            let e = parser::parse_formula(rest, interned, history)?;
+            println!("{} = {}", name, utils::expr_to_string(&e, interned));
+            history.define(&name, e);
+            return Ok(());
+        }
+
+        if let Some(arg) = input.strip_prefix("std ").or((input == "std").then_some("")) {
+            match arg.trim() {
+                "" => {
+                    let names = stdlib::names(rule_bindings);
+                    if names.is_empty() {
+                        println!("The standard library is not loaded, use 'std on' to load it");
+                    }
+                    for name in names {
+                        println!("{} := {}", name, utils::rule_to_string(&rule_bindings[&name], stdlib::patterns()));
+                    }
+                },
+                "on" => {
+                    for msg in stdlib::load(rule_bindings) {
                         println!("{}", msg);
-                        "".to_string()
                     }
+                    println!("Loaded {} standard laws", stdlib::names(rule_bindings).len());
+                },
+                "off" => {
+                    stdlib::unload(rule_bindings);
+                    println!("Removed the standard laws");
+                },
+                name => {
+                    let name = if name.starts_with(stdlib::NAMESPACE) { name.to_string() } else { format!("{}{}", stdlib::NAMESPACE, name) };
+                    match rule_bindings.get(&name) {
+                        Some(rule @ Rule::Equivalence(lhs, rhs)) => {
+                            println!("{} := {}", name, utils::rule_to_string(rule, stdlib::patterns()));
+                            let sound = runtime::is_equivalent(lhs, rhs, stdlib::patterns())?;
+                            *failed |= !sound;
+                            println!("sound: {}", sound);
+                        },
+                        _ => return Err(format!("Unknown standard law {}", name)),
+                    }
+                },
+            }
+            return Ok(());
+        }
+
+        if let Some(name) = input.strip_prefix("logic ").or((input == "logic").then_some("")) {
+            match name.trim() {
+                "" => println!("Current logic: {}", *logic),
+                name => match Logic::from_name(name) {
+                    Some(l) => {
+                        *logic = l;
+                        println!("Changed logic to {}", *logic);
+                    },
+                    None => return Err(format!("Unknown logic '{}', expected one of {}", name, logic::LOGICS)),
+                },
+            }
+            return Ok(());
+        }
+
+        if let Some(name) = input.strip_prefix("tnorm ").or((input == "tnorm").then_some("")) {
+            match name.trim() {
+                "" => println!("Current t-norm: {}", tnorm),
+                name => match TNorm::from_name(name) {
+                    Some(t) => {
+                        *tnorm = t;
+                        println!("Changed t-norm to {}", tnorm);
+                    },
+                    None => return Err(format!("Unknown t-norm '{}', expected one of {}", name, fuzzy::TNORMS)),
+                },
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("eval ") {
+            let Some((rest, assignments)) = rest.split_once(" with ") else {
+                return Err("Expected 'eval <expr> with p=0.5, ..'".to_string());
+            };
+            let res = parser::parse_formula(rest, interned, history).and_then(|e| {
+                let valuation = utils::parse_assignments(assignments, interned)?;
+                fuzzy::eval(&e, *tnorm, &valuation, interned)
+            });
+            match res {
+                Ok(v) => println!("{} ({})", v, tnorm),
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("surface ") {
+            let (rest, steps) = match rest.split_once(" steps ") {
+                Some((rest, steps)) => (rest, steps.trim().parse::<usize>().ok().filter(|s| *s > 0)),
+                None => (rest, Some(4)),
+            };
+            let Some(steps) = steps else {
+                return Err("Expected a positive number of steps".to_string());
+            };
+            parser::parse_formula(rest, interned, history).and_then(|e| fuzzy::print_surface(&e, *tnorm, steps, interned))?;
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("tableau ") {
+            match parser::parse_formula(rest, interned, history) {
+                Ok(e) => {
+                    let tree = tableau::build(&e);
+                    tree.print(interned);
+                    match tree.open_branch() {
+                        None => println!("valid: every branch of the tableau of the negation closes"),
+                        Some(branch) => {
+                            *failed = true;
+                            println!("not valid, countermodel: {}", tableau::countermodel(&branch, &e, interned));
+                        },
+                    }
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("resolve ") {
+            let res = parse_argument(rest, interned, history)
+                .and_then(|(premises, goal)| resolution::refute(&premises, &goal));
+            match res {
+                Ok(derivation) => {
+                    derivation.print(interned);
+                    if derivation.refutation.is_some() {
+                        println!("the goal follows: the empty clause was derived");
+                    } else {
+                        *failed = true;
+                        println!("the goal does not follow: saturated without the empty clause");
+                    }
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("sequent ") {
+            let (latex, rest) = match rest.trim_start().strip_prefix("latex ") {
+                Some(rest) => (true, rest),
+                None => (false, rest),
+            };
+            match parse_argument(rest, interned, history) {
+                Ok((premises, goal)) => {
+                    let seq = sequent::Sequent::new(&premises, &goal);
+                    let proof = sequent::search(seq);
+                    if latex {
+                        proof.print_latex(interned);
+                    } else {
+                        proof.print(interned);
+                    }
+                    match proof.failing_leaf() {
+                        None => println!("provable: every leaf is an axiom"),
+                        Some(leaf) => {
+                            *failed = true;
+                            println!("not provable, failing leaf {} gives countermodel: {}", 
+                                leaf.to_string(interned), 
+                                sequent::countermodel(leaf, interned));
+                        },
+                    }
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(path) = input.strip_prefix("fitch ") {
+            let res = std::fs::read_to_string(path.trim())
+                .map_err(|e| format!("Could not read {}: {}", path.trim(), e))
+                .and_then(|text| fitch::check(&text, interned));
+            match res {
+                Ok((premises, conclusion)) => {
+                    let argument = Expr::Argument(premises, Box::new(conclusion));
+                    println!("valid proof of {}", utils::expr_to_string(&argument, interned));
+                },
+                Err(msg) => return Err(format!("invalid proof, {}", msg)),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("tautology ") {
+            match parser::parse_formula(rest, interned, history) {
+                Ok(e) => {
+                    let mut table = runtime::Table::with_logic(interned, is_num_symbol, *logic);
+                    table.generate_truthtable(e)?;
+                    *failed |= !table.is_tautology();
+                    println!("tautology in {}: {}", logic, table.is_tautology());
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("equivalent ") {
+            let Some((left, right)) = rest.split_once(',') else {
+                return Err("Expected 'equivalent <expr>, <expr>'".to_string());
+            };
+            match (parser::parse_formula(left, interned, history), parser::parse_formula(right, interned, history)) {
+                (Ok(l), Ok(r)) => {
+                    let equivalent = runtime::is_equivalent_in(&l, &r, interned, *logic)?;
+                    *failed |= !equivalent;
+                    println!("equivalent in {}: {}", logic, equivalent);
+                },
+                (Err(msg), _) | (_, Err(msg)) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("chain ") {
+            let Some((left, right)) = rest.split_once(',') else {
+                return Err("Expected 'chain <expr>, <expr>'".to_string());
+            };
+            let res = parser::parse_formula(left, interned, history).and_then(|start| {
+                let goal = parser::parse_formula(right, interned, history)?;
+                Ok((start.clone(), chain::find(&start, &goal, rule_bindings, interned)?))
+            });
+            match res {
+                Ok((start, steps)) => {
+                    let lines = steps.iter().map(|(e, _)| utils::expr_to_string(e, interned)).collect::<Vec<_>>();
+                    let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+                    println!("  {}", utils::expr_to_string(&runtime::strip_groups(&start), interned));
+                    for (line, (_, name)) in lines.iter().zip(steps.iter()) {
+                        println!("= {:<width$}   [{}]", line, name, width = width);
+                    }
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("simplify ") {
+            match parser::parse_formula(rest, interned, history) {
+                Ok(e) => {
+                    let res = simplify::simplify(&e);
+                    let equivalent = runtime::is_equivalent(&e, &res, interned)?;
+                    let n = history.push(res.clone());
+                    println!("${}: {}", n, utils::expr_to_string(&res, interned));
+                    println!("cost {} -> {}, equivalent to input: {}", 
+                        simplify::cost(&e).0, 
+                        simplify::cost(&res).0, 
+                        equivalent);
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("minimize ") {
+            let (rest, dc) = split_dont_cares(rest);
+            match (parser::parse_formula(rest, interned, history), dc) {
+                (Ok(e), Ok(dc)) => {
+                    let mut table = runtime::Table::new(interned, is_num_symbol);
+                    table.generate_truthtable(e)?;
+                    minimize::check_rows(&dc, table.atoms().len())?;
+                    let (sop, pos) = minimize::minimize(table.result(), &dc, table.atoms())?;
+                    let n = history.push(sop.clone());
+                    println!("minimal SOP ${}: {}", n, utils::expr_to_string(&sop, interned));
+                    let n = history.push(pos.clone());
+                    println!("minimal POS ${}: {}", n, utils::expr_to_string(&pos, interned));
+                },
+                (Err(msg), _) | (_, Err(msg)) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("kmap ") {
+            let (rest, dc) = split_dont_cares(rest);
+            match (parser::parse_formula(rest, interned, history), dc) {
+                (Ok(e), Ok(dc)) => {
+                    let mut table = runtime::Table::new(interned, is_num_symbol);
+                    table.generate_truthtable(e)?;
+                    minimize::check_rows(&dc, table.atoms().len())?;
+                    kmap::print(table.result(), &dc, table.atoms(), interned)?;
+                },
+                (Err(msg), _) | (_, Err(msg)) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("basis ") {
+            let (basis, rest) = match rest.trim_start().strip_prefix('{') {
+                Some(set) => set.split_once('}').unwrap_or((set, "")),
+                None => rest.trim_start().split_once(' ').unwrap_or((rest, "")),
+            };
+            match parser::parse_formula(rest, interned, history).and_then(|e| basis::to_basis(&e, basis).map(|res| (e, res))) {
+                Ok((e, res)) => {
+                    let equivalent = runtime::is_equivalent(&e, &res, interned)?;
+                    let n = history.push(res.clone());
+                    println!("${}: {}", n, utils::expr_to_string(&res, interned));
+                    println!("equivalent to input: {}", equivalent);
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(set) = input.strip_prefix("complete ") {
+            match basis::check_completeness(set) {
+                Ok((true, _)) => println!("{} is functionally complete", set.trim()),
+                Ok((false, closed)) => println!("{} is not functionally complete: all connectives {}", set.trim(), closed.join(", ")),
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("count ") {
+            match parser::parse_formula(rest, interned, history).and_then(|e| counting::count(&e, interned)) {
+                Ok(res) => {
+                    let method = match res.method {
+                        counting::Method::Table => "truth table",
+                        counting::Method::Bdd => "BDD",
+                    };
+                    println!("{} of {} assignments satisfy the expression ({})", res.models, 1u128 << res.atoms, method);
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = input.strip_prefix("prob ") {
+            let Some((rest, assignments)) = rest.split_once(" with ") else {
+                return Err("Expected 'prob <expr> with p=0.5, ..'".to_string());
+            };
+            let res = parser::parse_formula(rest, interned, history).and_then(|e| {
+                let probs = utils::parse_assignments(assignments, interned)?;
+                counting::probability(&e, &probs, interned)
+            });
+            match res {
+                Ok((p, counting::Method::Table)) => println!("P = {} (truth table)", p),
+                Ok((p, counting::Method::Bdd)) => println!("P = {} (BDD)", p),
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        if synth::is_spec(input) {
+            match synth::parse_spec(input) {
+                Ok(spec) => {
+                    let res = synth::synthesize(&spec, interned);
+                    let n = history.push(res.dnf.clone());
+                    println!("canonical DNF ${}: {}", n, utils::expr_to_string(&res.dnf, interned));
+                    let n = history.push(res.cnf.clone());
+                    println!("canonical CNF ${}: {}", n, utils::expr_to_string(&res.cnf, interned));
+                    let n = history.push(res.minimal.clone());
+                    println!("minimal SOP   ${}: {}", n, utils::expr_to_string(&res.minimal, interned));
+                },
+                Err(msg) => return Err(msg),
+            }
+            return Ok(());
+        }
+
+        let expr = parser::parse_line(input, interned, history);
+
+        match expr {
+            Ok(parser::Expr::Pattern(e, rule)) => {
+                let res = runtime::match_patterns(&e, *rule, interned, rule_bindings)?;
+                let n = history.push(res.clone());
+                println!("${}: {}", n, utils::expr_to_string(&res, interned));
+            },
+            Ok(parser::Expr::Argument(premises, conclusion)) => {
+                if argument::check(&premises, &conclusion, interned, is_num_symbol, *logic)? {
+                    println!("valid: the conclusion is true in every critical row");
+                } else {
+                    *failed = true;
+                    println!("invalid: the conclusion is false in a critical row");
                 }
             },
             Ok(parser::Expr::Binding(id, rule)) => {
                 if let Expr::Primary(n) = *id {
                     rule_bindings.insert(interned[n].clone(), *rule);
                 } else {
-                    println!("Unreachable");
+                    return Err("Unreachable".to_string());
                 }
             }
             Ok(e) => {
-                let mut table = runtime::Table::new(&interned, &is_num_symbol);
-                prev_input = utils::expr_to_string(&e, &interned);
-                table.generate_truthtable(e);
+                let mut table = runtime::Table::with_logic(interned, is_num_symbol, *logic);
+                table.generate_truthtable(e.clone())?;
+                let n = history.push(e);
                 table.print();
+                println!("(saved as ${})", n);
             },
-            Err(msg) => println!("{}", msg),
+            Err(msg) => return Err(msg),
         }
+        Ok(())
     }
+}
+
+// Exit codes when plogic runs a script or expressions from the command line
+const EXIT_FAILURE: i32 = 1;    // an error or a failed check
+const EXIT_USAGE: i32 = 2;      // invalid command line arguments
 
+fn main() {
+    let mut session = Session::new();
+
+    let mut no_std = false;
+    let mut paths = Vec::new();
+    let mut inputs = Vec::new();
+    let mut script = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), script.is_none()) {
+            ("--no-std", _) => no_std = true,
+            ("--load", _) => match args.next() {
+                Some(path) => paths.push(path),
+                None => {
+                    eprintln!("Expected a path after --load");
+                    std::process::exit(EXIT_USAGE);
+                },
+            },
+            ("-e", _) => match args.next() {
+                Some(input) => inputs.push(input),
+                None => {
+                    eprintln!("Expected an input after -e");
+                    std::process::exit(EXIT_USAGE);
+                },
+            },
+            ("-h" | "--help", _) => {
+                utils::cli_usage();
+                return;
+            },
+            (path, true) if !path.starts_with('-') => script = Some(arg),
+            _ => {
+                eprintln!("Unknown argument {}", arg);
+                utils::cli_usage();
+                std::process::exit(EXIT_USAGE);
+            },
+        }
+    }
+
+    if !no_std {
+        for msg in stdlib::load(&mut session.rule_bindings) {
+            println!("{}", msg);
+        }
+    }
+    let mut ok = true;
+    for path in paths {
+        if let Err(msg) = session.load(&path) {
+            eprintln!("{}", msg);
+            ok = false;
+        }
+    }
+
+    // Without a terminal, e.g. with piped input, there is no banner and no prompt
+    if !inputs.is_empty() || script.is_some() || !io::stdin().is_terminal() {
+        let mut quit = false;
+        for input in &inputs {
+            let input = input.trim();
+            if input == "quit" && session.derivation.is_none() {
+                quit = true;
+                break;
+            }
+            if let Err(msg) = session.execute(input) {
+                eprintln!("{}", msg);
+                ok = false;
+            }
+        }
+        let res = match (script, inputs.is_empty()) {
+            _ if quit => Ok(()),
+            (Some(path), _) => session.load(&path),
+            (None, true) => io::read_to_string(io::stdin())
+                .map_err(|e| format!("Could not read stdin: {}", e))
+                .and_then(|text| session.run("<stdin>", &text)),
+            (None, false) => Ok(()),
+        };
+        if let Err(msg) = res {
+            eprintln!("{}", msg);
+            ok = false;
+        }
+        if session.derivation.take().is_some() {
+            eprintln!("derivation is missing 'end'");
+            ok = false;
+        }
+        if !ok || session.failed {
+            std::process::exit(EXIT_FAILURE);
+        }
+        return;
+    }
+
+    println!("Welcome to the REPL of Plogic.");
+    utils::usage();
+    let mut reader = editor::LineReader::new();
+    loop {
+        let prompt = if session.derivation.is_some() { "| " } else { "> " };
+        let Some(input) = reader.read_line(prompt, session.rule_bindings.keys().cloned().collect()) else {
+            println!();
+            break;
+        };
+        
+        let input = input.trim();
+        if input == "quit" && session.derivation.is_none() {
+            break;
+        }
+        if let Err(msg) = session.execute(input) {
+            println!("{}", msg);
+        }
+    }
+    reader.save_history();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut session = Session::new();
+        stdlib::load(&mut session.rule_bindings);
+        session
+    }
+
+    // A file in the temporary directory that is removed when the test ends
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str, text: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("plogic-{}-{}.plg", std::process::id(), name));
+            let path = path.to_str().unwrap().to_string();
+            std::fs::write(&path, text).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn load_runs_every_line_of_a_file() {
+        let file = TempFile::new("load", "# rules and definitions\nswap := a & b = b & a\nlet x = p | q  # a comment\nx & r\n");
+        let mut session = session();
+        session.execute(&format!("load {}", file.0)).unwrap();
+        assert!(session.rule_bindings.contains_key("swap"));
+        assert!(session.history.definition("x").is_some());
+        assert_eq!(session.history.entries().len(), 1);
+    }
+
+    #[test]
+    fn load_reports_the_failing_lines_and_recursion() {
+        let file = TempFile::new("recursive", "");
+        std::fs::write(&file.0, format!("p &\nload {}\n", file.0)).unwrap();
+        let mut session = session();
+        assert_eq!(session.execute(&format!("load {}", file.0)), Err(format!("{}: 2 error(s)", file.0)));
+        assert!(session.loading.is_empty());
+        assert!(session.execute("load /nonexistent/file.plg").unwrap_err().starts_with("Could not read"));
+    }
+
+    #[test]
+    fn definitions_can_be_replaced() {
+        let mut session = session();
+        session.execute("let x = p").unwrap();
+        session.execute("let x = x & q").unwrap();
+        let x = session.history.definition("x").unwrap();
+        assert_eq!(utils::expr_to_string(x, &session.interned), "p & q");
+        assert!(session.execute("let 1 = p").is_err());
+    }
+
+    #[test]
+    fn definitions_are_not_captured_by_quantifiers() {
+        let mut session = session();
+        session.execute("let q = p").unwrap();
+        let err = session.execute("tautology forall p. q <-> p").unwrap_err();
+        assert!(err.starts_with("q has the free atom p, which the quantifier around it would capture"));
+        session.execute("p & q").unwrap();
+        assert!(session.execute("exists p. ans").is_err());
+        session.execute("exists r. q & r").unwrap();
+    }
+
+    #[test]
+    fn rule_names_are_not_atoms() {
+        let mut session = session();
+        session.execute("comm := a & b = b & a").unwrap();
+        session.execute("x & y => comm").unwrap();
+        let atoms = atom_names(&session.interned, &session.rule_bindings);
+        assert_eq!(atoms, vec!["a", "b", "x", "y"]);
+    }
+
+    #[test]
+    fn save_writes_only_atoms_to_the_atoms_line() {
+        let file = TempFile::new("atoms", "");
+        let mut session = session();
+        session.execute("comm := a & b = b & a").unwrap();
+        session.save(&file.0).unwrap();
+        let text = std::fs::read_to_string(&file.0).unwrap();
+        assert!(text.lines().any(|line| line == "atoms a, b"));
+        assert!(text.lines().any(|line| line == "std on"));
+        assert!(!text.contains("std::"));
+    }
+
+    #[test]
+    fn restore_brings_back_a_saved_session() {
+        let file = TempFile::new("session", "");
+        let mut session = session();
+        for line in ["toggle", "logic k3", "tnorm product", "atoms z", "swap := a & b = b & a", "b & c", "let d = $1 | z"] {
+            session.execute(line).unwrap();
+        }
+        session.save(&file.0).unwrap();
+
+        let mut restored = Session::new();
+        restored.execute(&format!("restore {}", file.0)).unwrap();
+        assert!(!restored.is_num_symbol);
+        assert_eq!((restored.logic, restored.tnorm), (Logic::Kleene, TNorm::Product));
+        assert_eq!(atom_names(&restored.interned, &restored.rule_bindings), atom_names(&session.interned, &session.rule_bindings));
+        assert_eq!(restored.rule_bindings.len(), session.rule_bindings.len());
+        let print = |s: &Session| s.history.entries().iter().map(|e| utils::expr_to_string(e, &s.interned)).collect::<Vec<_>>();
+        assert_eq!(print(&restored), print(&session));
+        let d = restored.history.definition("d").unwrap();
+        assert_eq!(utils::expr_to_string(d, &restored.interned), "b & c | z");
+    }
+
+    #[test]
+    fn failed_restore_keeps_the_session() {
+        let file = TempFile::new("broken", "let x = p\np &\n");
+        let mut session = session();
+        session.execute("let y = q").unwrap();
+        assert!(session.execute(&format!("restore {}", file.0)).is_err());
+        assert!(session.history.definition("y").is_some());
+        assert!(session.history.definition("x").is_none());
+    }
+
+    #[test]
+    fn quit_inside_a_derivation_does_not_end_the_script() {
+        let mut session = session();
+        let res = session.run("test", "derivation\np & q\nquit\nend\nlet x = p");
+        assert!(res.is_err());
+        assert!(session.history.definition("x").is_some());
+    }
+
+    #[test]
+    fn quit_ends_the_script() {
+        let mut session = session();
+        assert!(session.run("test", "let x = p\nquit\nlet y = p").is_ok());
+        assert!(session.history.definition("x").is_some());
+        assert!(session.history.definition("y").is_none());
+    }
+
+    #[test]
+    fn run_counts_errors_and_open_derivations() {
+        let mut session = session();
+        assert_eq!(session.run("test", "p &\nend\nderivation"), Err("test: 3 error(s)".to_string()));
+        assert!(session.derivation.is_none());
+    }
+
+    #[test]
+    fn end_needs_an_open_derivation() {
+        let mut session = session();
+        assert!(session.execute("end").is_err());
+        session.execute("derivation").unwrap();
+        session.execute("p & q").unwrap();
+        session.execute("q & p [std::commAnd]").unwrap();
+        assert!(session.execute("end").is_ok());
+        assert!(session.derivation.is_none());
+    }
+
+    #[test]
+    fn failed_checks_are_recorded_for_the_exit_code() {
+        let mut session = session();
+        session.execute("tautology p | ~p").unwrap();
+        session.execute("p -> q, p |- q").unwrap();
+        assert!(!session.failed);
+        session.execute("p -> q, q |- p").unwrap();
+        assert!(session.failed);
+    }
 }
@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use crate::parser::{Expr, BinOperator};
+
+// Quine-McCluskey and Petrick's method grow exponentially, beyond this many variables they take too long
+pub const MAX_VARS: usize = 6;
+
+// A product term over n variables: bits set in `mask` are eliminated, the other bits must equal `value`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct Implicant {
+    pub value: usize,
+    pub mask: usize,
+}
+
+impl Implicant {
+    pub fn covers(&self, minterm: usize) -> bool {
+        minterm & !self.mask == self.value
+    }
+
+    pub fn literals(&self, vars: usize) -> usize {
+        vars - self.mask.count_ones() as usize
+    }
+
+    fn combine(&self, other: &Implicant) -> Option<Implicant> {
+        let diff = self.value ^ other.value;
+        if self.mask == other.mask && diff.count_ones() == 1 {
+            Some(Implicant { value: self.value & !diff, mask: self.mask | diff })
+        } else {
+            None
+        }
+    }
+}
+
+pub fn prime_implicants(on: &[usize], dc: &[usize]) -> Vec<Implicant> {
+    let mut current: HashSet<Implicant> = on
+        .iter()
+        .chain(dc.iter())
+        .map(|m| Implicant { value: *m, mask: 0 })
+        .collect();
+    let mut primes: Vec<Implicant> = Vec::new();
+
+    while !current.is_empty() {
+        let terms = current.iter().copied().collect::<Vec<_>>();
+        let mut combined: HashSet<Implicant> = HashSet::new();
+        let mut used: HashSet<Implicant> = HashSet::new();
+        for (i, a) in terms.iter().enumerate() {
+            for b in &terms[i+1..] {
+                if let Some(c) = a.combine(b) {
+                    combined.insert(c);
+                    used.insert(*a);
+                    used.insert(*b);
+                }
+            }
+        }
+        primes.extend(terms.iter().filter(|t| !used.contains(t)));
+        current = combined;
+    }
+    // Implicants that only cover don't-care rows are never needed
+    primes.retain(|p| on.iter().any(|m| p.covers(*m)));
+    primes.sort();
+    primes
+}
+
+// Cheapest set of primes covering the minterms, fewest primes first and fewest literals second. This is the
+// minimum of Petrick's method, searched depth first instead of multiplying out its product of sums
+fn petrick(primes: &[Implicant], minterms: &[usize], vars: usize) -> Vec<usize> {
+    let covering = minterms
+        .iter()
+        .map(|m| (0..primes.len()).filter(|i| primes[*i].covers(*m)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut best = None;
+    search(primes, &covering, vars, &mut Vec::new(), &mut best);
+    best.map(|(_, chosen)| chosen).unwrap_or_default()
+}
+
+type Cover = ((usize, usize), Vec<usize>);
+
+fn search(primes: &[Implicant], uncovered: &[Vec<usize>], vars: usize, chosen: &mut Vec<usize>, best: &mut Option<Cover>) {
+    let literals = chosen.iter().map(|i| primes[*i].literals(vars)).sum::<usize>();
+    if best.as_ref().is_some_and(|(cost, _)| (chosen.len() + lower_bound(uncovered), literals) >= *cost) {
+        return;
+    }
+    // Branch on the minterm with the fewest primes covering it
+    let Some(next) = uncovered.iter().min_by_key(|c| c.len()) else {
+        let mut cover = chosen.clone();
+        cover.sort();
+        *best = Some(((chosen.len(), literals), cover));
+        return;
+    };
+    for i in next.clone() {
+        let rest = uncovered.iter().filter(|c| !c.contains(&i)).cloned().collect::<Vec<_>>();
+        chosen.push(i);
+        search(primes, &rest, vars, chosen, best);
+        chosen.pop();
+    }
+}
+
+// Minterms without a common prime each need a prime of their own
+fn lower_bound(uncovered: &[Vec<usize>]) -> usize {
+    let mut independent: Vec<&Vec<usize>> = Vec::new();
+    for c in uncovered {
+        if independent.iter().all(|other| !other.iter().any(|i| c.contains(i))) {
+            independent.push(c);
+        }
+    }
+    independent.len()
+}
+
+pub fn minimal_cover(vars: usize, on: &[usize], dc: &[usize]) -> Vec<Implicant> {
+    let primes = prime_implicants(on, dc);
+    let mut cover: Vec<Implicant> = Vec::new();
+
+    // Essential prime implicants are the only prime covering some minterm
+    for m in on {
+        let covering = primes.iter().filter(|p| p.covers(*m)).collect::<Vec<_>>();
+        if covering.len() == 1 && !cover.contains(covering[0]) {
+            cover.push(*covering[0]);
+        }
+    }
+
+    let remaining = on
+        .iter()
+        .copied()
+        .filter(|m| !cover.iter().any(|p| p.covers(*m)))
+        .collect::<Vec<_>>();
+    let candidates = primes
+        .iter()
+        .copied()
+        .filter(|p| !cover.contains(p))
+        .collect::<Vec<_>>();
+    for i in petrick(&candidates, &remaining, vars) {
+        cover.push(candidates[i]);
+    }
+    cover.sort();
+    cover
+}
+
+fn fold(terms: Vec<Expr>, op: BinOperator, empty: bool) -> Expr {
+    terms
+        .into_iter()
+        .reduce(|acc, e| Expr::Binary(Box::new(acc), op, Box::new(e)))
+        .unwrap_or(Expr::Constant(empty))
+}
+
+fn group(terms: Vec<Expr>) -> Vec<Expr> {
+    if terms.len() < 2 {
+        return terms;
+    }
+    terms
+        .into_iter()
+        .map(|e| match e {
+            e @ Expr::Binary(..) => Expr::Group(Box::new(e)),
+            e => e,
+        })
+        .collect()
+}
+
+// Variable j of `atoms` is bit j of a row index, as in the rows of a truth table.
+// Literals are emitted from the highest bit down, in the order of the truth table columns
+fn literal(atom: &Expr, positive: bool) -> Expr {
+    if positive {
+        atom.clone()
+    } else {
+        Expr::Not(Box::new(atom.clone()))
+    }
+}
+
+pub fn implicant_to_expr(implicant: &Implicant, atoms: &[Expr]) -> Expr {
+    let literals = atoms
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(j, _)| implicant.mask & (1 << j) == 0)
+        .map(|(j, atom)| literal(atom, implicant.value & (1 << j) != 0))
+        .collect::<Vec<_>>();
+    fold(literals, BinOperator::And, true)
+}
+
+pub fn to_sop(cover: &[Implicant], atoms: &[Expr]) -> Expr {
+    let terms = cover
+        .iter()
+        .map(|p| implicant_to_expr(p, atoms))
+        .collect::<Vec<_>>();
+    fold(group(terms), BinOperator::Or, false)
+}
+
+// `cover` is a cover of the zeros of the function, each implicant becomes a sum of negated literals
+pub fn to_pos(cover: &[Implicant], atoms: &[Expr]) -> Expr {
+    let terms = cover
+        .iter()
+        .map(|p| {
+            let literals = atoms
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(j, _)| p.mask & (1 << j) == 0)
+                .map(|(j, atom)| literal(atom, p.value & (1 << j) == 0))
+                .collect::<Vec<_>>();
+            fold(literals, BinOperator::Or, false)
+        })
+        .collect::<Vec<_>>();
+    fold(group(terms), BinOperator::And, true)
+}
+
+// Don't-care rows are row numbers of the truth table of `vars` atoms
+pub fn check_rows(rows: &[usize], vars: usize) -> Result<(), String> {
+    match rows.iter().find(|r| **r >= 1 << vars) {
+        Some(row) => Err(format!("Row {} does not exist for {} variables", row, vars)),
+        None => Ok(()),
+    }
+}
+
+pub fn check_vars(vars: usize) -> Result<(), String> {
+    if vars > MAX_VARS {
+        Err(format!("Minimization supports at most {} variables, found {}", MAX_VARS, vars))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn minimize(result: &[usize], dc: &[usize], atoms: &[Expr]) -> Result<(Expr, Expr), String> {
+    let vars = atoms.len();
+    check_vars(vars)?;
+    let on = (0..result.len()).filter(|i| result[*i] == 1 && !dc.contains(i)).collect::<Vec<_>>();
+    let off = (0..result.len()).filter(|i| result[*i] == 0 && !dc.contains(i)).collect::<Vec<_>>();
+    let sop = to_sop(&minimal_cover(vars, &on, dc), atoms);
+    let pos = to_pos(&minimal_cover(vars, &off, dc), atoms);
+    Ok((sop, pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cover_size(vars: usize, on: &[usize], dc: &[usize]) -> (usize, usize) {
+        let cover = minimal_cover(vars, on, dc);
+        for m in on {
+            assert!(cover.iter().any(|p| p.covers(*m)), "minterm {} is not covered", m);
+        }
+        for p in &cover {
+            for row in 0..1 << vars {
+                assert!(!p.covers(row) || on.contains(&row) || dc.contains(&row), "row {} is covered", row);
+            }
+        }
+        (cover.len(), cover.iter().map(|p| p.literals(vars)).sum())
+    }
+
+    #[test]
+    fn combines_adjacent_minterms() {
+        assert_eq!(prime_implicants(&[0, 1, 2, 3], &[]), vec![Implicant { value: 0, mask: 3 }]);
+        assert_eq!(cover_size(3, &[0, 1, 2, 3], &[]), (1, 1));
+    }
+
+    #[test]
+    fn petrick_picks_the_cheapest_cyclic_cover() {
+        // Every minterm is covered by two primes and none of them is essential
+        assert_eq!(cover_size(3, &[0, 1, 2, 5, 6, 7], &[]), (3, 6));
+        assert_eq!(cover_size(4, &[0, 2, 5, 7, 8, 10, 13, 15], &[]), (2, 4));
+    }
+
+    #[test]
+    fn dont_cares_only_widen_implicants() {
+        assert_eq!(cover_size(2, &[1], &[3]), (1, 1));
+        assert_eq!(prime_implicants(&[], &[0, 1]), Vec::new());
+        assert_eq!(minimal_cover(2, &[], &[1]), Vec::new());
+    }
+
+    #[test]
+    fn rejects_rows_outside_the_table() {
+        assert!(check_rows(&[0, 3], 2).is_ok());
+        assert!(check_rows(&[4], 2).is_err());
+        assert!(check_rows(&[7], 2).is_err());
+        assert!(check_rows(&[1], 0).is_err());
+    }
+
+    #[test]
+    fn literals_follow_the_truth_table_columns() {
+        // Atom j is bit j of a row number, the leftmost column is the highest bit
+        let atoms = (0..3).map(Expr::Primary).collect::<Vec<_>>();
+        let implicant = Implicant { value: 0b100, mask: 0b010 };
+        let mut interned = crate::interner::Interner::new();
+        for name in ["a", "b", "c"] {
+            interned.intern(name);
+        }
+        assert_eq!(crate::utils::expr_to_string(&implicant_to_expr(&implicant, &atoms), &interned), "c & ~a");
+        assert_eq!(crate::utils::expr_to_string(&to_pos(&[implicant], &atoms), &interned), "~c | a");
+    }
+
+    #[test]
+    fn rejects_too_many_variables() {
+        let atoms = (0..MAX_VARS + 1).map(Expr::Primary).collect::<Vec<_>>();
+        let result = vec![0; 1 << atoms.len()];
+        assert!(minimize(&result, &[], &atoms).is_err());
+        assert!(minimize(&result[..1 << MAX_VARS], &[], &atoms[..MAX_VARS]).is_ok());
+    }
+}
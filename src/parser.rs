@@ -1,5 +1,5 @@
 use std::{fmt::Display, iter::Peekable, slice::Iter};
-use crate::lexer::Token;
+use crate::{lexer::{self, Token, TokenKind}, history::History, interner::Interner, error::{Error, Span}, runtime, utils};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
 pub enum BinOperator {
@@ -9,6 +9,17 @@ pub enum BinOperator {
     TwinArrow,              // <->
 }
 
+impl BinOperator {
+    pub fn precedence(&self) -> usize {
+        match self {
+            BinOperator::And => 4,
+            BinOperator::Or => 3,
+            BinOperator::Arrow => 2,
+            BinOperator::TwinArrow => 1,
+        }
+    }
+}
+
 impl Display for BinOperator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -20,6 +31,21 @@ impl Display for BinOperator {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum Quantifier {
+    Forall,                 // forall p. e
+    Exists,                 // exists p. e
+}
+
+impl Display for Quantifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quantifier::Forall => write!(f, "forall"),
+            Quantifier::Exists => write!(f, "exists"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub enum Rule {
     Equivalence(Expr, Expr),
@@ -30,141 +56,385 @@ pub enum Rule {
 pub enum Expr {
     Pattern(Box<Expr>, Box<Rule>),              // Binary => Equivalence // r & s => p & q = q & p
     Binding(Box<Expr>, Box<Rule>),              // switch := p & q = q & p // x & y => switch  
+    Argument(Vec<Expr>, Box<Expr>),             // p -> q, p |- q
     Binary(Box<Expr>, BinOperator, Box<Expr>),
     Not(Box<Expr>),
     Group(Box<Expr>),
+    Quantified(Quantifier, usize, Box<Expr>),   // forall p. p | q
     Primary(usize),
+    Constant(bool),
+}
+
+type Tokens<'a> = Peekable<Iter<'a, Token>>;
+
+fn peek<'a>(tokens: &mut Tokens<'a>) -> Option<&'a TokenKind> {
+    tokens.peek().map(|t| &t.kind)
+}
+
+fn expected(token: Option<&Token>, expected: &'static str) -> Error {
+    match token {
+        Some(t) => Error::Expected { expected, found: t.kind.to_string(), span: t.span },
+        None => Error::Expected { expected, found: TokenKind::Eof.to_string(), span: Span::default() },
+    }
+}
+
+// Decides which names are replaced by their definitions
+struct Scope<'a> {
+    history: &'a History,
+    bound: Vec<String>,     // atoms bound by the quantifiers around the current position
+    rule: bool,             // inside the sides of a rule, where every name is a pattern variable
 }
 
-pub fn parse(list: &mut Vec<Token>, interned: &mut Vec<String>) -> Result<Expr, String> {
+impl Scope<'_> {
+    fn definition(&self, name: &str) -> Option<&Expr> {
+        if self.rule || self.bound.iter().any(|b| b == name) {
+            None
+        } else {
+            self.history.definition(name)
+        }
+    }
+
+    fn in_rule<T>(&mut self, rule: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = self.rule;
+        self.rule |= rule;
+        let res = f(self);
+        self.rule = outer;
+        res
+    }
+}
+
+pub fn parse(list: &mut [Token], interned: &mut Interner, history: &History) -> Result<Expr, Error> {
     let mut tokens = list.iter().peekable();
-    let res = pattern_match(&mut tokens, interned)?;
-    if let Some(t) = tokens.peek() {
-        Err(format!("Unexpected token, expected end of input: {:?}", t))
-    } else {
-        Ok(res)
+    let mut scope = Scope { history, bound: Vec::new(), rule: false };
+    let res = argument(&mut tokens, interned, &mut scope)?;
+    match tokens.next() {
+        Some(Token { kind: TokenKind::Eof, .. }) | None => Ok(res),
+        other => Err(expected(other, "an operator or the end of input")),
+    }
+}
+
+// Tokenizes and parses a line of input, errors are rendered with a caret under the input
+pub fn parse_line(input: &str, interned: &mut Interner, history: &History) -> Result<Expr, String> {
+    let mut tokens: Vec<Token> = Vec::new();
+    lexer::tokenize(&mut tokens, input)
+        .and_then(|_| parse(&mut tokens, interned, history))
+        .map_err(|e| e.render(input))
+}
+
+// Parses a single propositional formula, statements such as rule bindings are rejected
+pub fn parse_formula(input: &str, interned: &mut Interner, history: &History) -> Result<Expr, String> {
+    match parse_line(input, interned, history)? {
+        e @ (Expr::Pattern(..) | Expr::Binding(..) | Expr::Argument(..)) => {
+            let span = Span { start: input.len() - input.trim_start().len(), end: input.trim_end().len() };
+            Err(Error::NotAFormula(utils::expr_to_string(&e, interned), span).render(input))
+        },
+        e => Ok(e),
+    }
+}
+
+// Parses a rule written as a binding, e.g. "demorgan := ~(p & q) = ~p | ~q", into its two sides
+pub fn parse_rule(input: &str, interned: &mut Interner) -> Result<(Expr, Expr), String> {
+    match parse_line(input, interned, &History::new())? {
+        Expr::Binding(_, rule) => match *rule {
+            Rule::Equivalence(lhs, rhs) => Ok((lhs, rhs)),
+            Rule::RuleId(_) => Err(format!("Expected a rule with two sides, found {}", input)),
+        },
+        _ => Err(format!("Expected a rule binding, found {}", input)),
+    }
+}
+
+// Premises separated by commas, followed by a turnstile and the conclusion
+fn argument(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let mut premises = Vec::new();
+    if let Some(TokenKind::Turnstile) = peek(tokens) {
+        tokens.next();
+        let conclusion = logic_twin_arrow(tokens, interned, scope)?;
+        return Ok(Expr::Argument(premises, Box::new(conclusion)));
+    }
+
+    let start = tokens.peek().map(|t| t.span.start).unwrap_or_default();
+    let first = pattern_match(tokens, interned, scope)?;
+    let Some(next) = tokens.peek().filter(|t| matches!(t.kind, TokenKind::Comma | TokenKind::Turnstile)) else {
+        return Ok(first);
+    };
+    // Only the whole input can be a rule, premises are formulas
+    if let Expr::Pattern(..) | Expr::Binding(..) = first {
+        let span = Span { start, end: next.span.start };
+        return Err(Error::NotAFormula(utils::expr_to_string(&first, interned), span));
+    }
+    premises.push(first);
+    while let Some(TokenKind::Comma) = peek(tokens) {
+        tokens.next();
+        premises.push(logic_twin_arrow(tokens, interned, scope)?);
+    }
+    match tokens.next() {
+        Some(Token { kind: TokenKind::Turnstile, .. }) => {
+            let conclusion = logic_twin_arrow(tokens, interned, scope)?;
+            Ok(Expr::Argument(premises, Box::new(conclusion)))
+        },
+        other => Err(expected(other, "'|-' or 'therefore' after the premises")),
     }
 }
 
-fn pattern_match(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
+fn pattern_match(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
     
-    let mut left = logic_twin_arrow(tokens, interned);
+    // The name of a rule binding is not replaced by a definition
+    let mut ahead = tokens.clone();
+    let is_binding = matches!((ahead.next().map(|t| &t.kind), ahead.next().map(|t| &t.kind)), (Some(TokenKind::Identifier(_)), Some(TokenKind::Binding)));
+    let mut left = scope.in_rule(is_binding, |scope| logic_twin_arrow(tokens, interned, scope))?;
     
-    if let Some(Token::Binding) = tokens.peek() {
+    if let Some(binding @ Token { kind: TokenKind::Binding, .. }) = tokens.peek() {
         
-        if let Ok(Expr::Primary(_)) = left {
+        if let Expr::Primary(_) = left {
             tokens.next();
-            let eq_lhs = logic_twin_arrow(tokens, interned)?;
-            if let Some(Token::Equal) = tokens.peek() {
+            let eq_lhs = scope.in_rule(true, |scope| logic_twin_arrow(tokens, interned, scope))?;
+            if let Some(TokenKind::Equal) = peek(tokens) {
                 tokens.next();
-                let eq_rhs = logic_twin_arrow(tokens, interned)?;
-                return Ok(Expr::Binding(Box::new(left?), Box::new(Rule::Equivalence(eq_lhs, eq_rhs))));
+                let eq_rhs = scope.in_rule(true, |scope| logic_twin_arrow(tokens, interned, scope))?;
+                return Ok(Expr::Binding(Box::new(left), Box::new(Rule::Equivalence(eq_lhs, eq_rhs))));
             } else {
-                return Err("Expected '=' in pattern expression".to_string());
+                return Err(expected(tokens.next(), "'=' between the sides of the rule"));
             }
         } else {
-            return Err(format!("Can only bind rule to identifier, found {:?}", left));
+            return Err(Error::NotAnAtom(utils::expr_to_string(&left, interned), binding.span));
         }
     }
     
-    while let Some(Token::Rule) = tokens.peek() {
+    while let Some(TokenKind::Rule) = peek(tokens) {
         tokens.next();
-        let eq_lhs = logic_twin_arrow(tokens, interned)?;
+        let start = tokens.peek().map(|t| t.span.start).unwrap_or_default();
+        let eq_lhs = scope.in_rule(true, |scope| logic_twin_arrow(tokens, interned, scope))?;
         match tokens.peek() {
-            Some(Token::Equal) => {
+            Some(Token { kind: TokenKind::Equal, .. }) => {
                 tokens.next();
-                let eq_rhs = logic_twin_arrow(tokens, interned)?;
-                left = Ok(Expr::Pattern(Box::new(left?), Box::new(Rule::Equivalence(eq_lhs, eq_rhs))));
+                let eq_rhs = scope.in_rule(true, |scope| logic_twin_arrow(tokens, interned, scope))?;
+                left = Expr::Pattern(Box::new(left), Box::new(Rule::Equivalence(eq_lhs, eq_rhs)));
             },
-            Some(other) => return Err(format!("Expected '=' in pattern expression or rule identifier, found {:?}", other)),
-            None => {
+            Some(Token { kind: TokenKind::Eof, span }) => {
                 if let Expr::Primary(n) = eq_lhs {
-                    return Ok(Expr::Pattern(Box::new(left?), Box::new(Rule::RuleId(n))));
+                    return Ok(Expr::Pattern(Box::new(left), Box::new(Rule::RuleId(n))));
                 } else {
-                    return Err(format!("Expected rule identifier name, but got {:?}", eq_lhs));
+                    let span = Span { start, end: span.start };
+                    return Err(Error::NotAnAtom(utils::expr_to_string(&eq_lhs, interned), span));
                 }
-            }
+            },
+            other => return Err(expected(other.copied(), "'=' in pattern expression or the end of input after a rule name")),
         }
     }
-    left
+    Ok(left)
 }
 
-fn logic_twin_arrow(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
-    let mut left = logic_arrow(tokens, interned);
+fn logic_twin_arrow(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let mut left = logic_arrow(tokens, interned, scope)?;
 
-    while let Some(Token::TwinArrow) = tokens.peek() {
+    while let Some(TokenKind::TwinArrow) = peek(tokens) {
         tokens.next();
-        let right = logic_arrow(tokens, interned)?;
-        left = Ok(Expr::Binary(Box::new(left?), BinOperator::TwinArrow, Box::new(right)));
+        let right = logic_arrow(tokens, interned, scope)?;
+        left = Expr::Binary(Box::new(left), BinOperator::TwinArrow, Box::new(right));
     }
-    left
+    Ok(left)
 }
 
-fn logic_arrow(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
-    let mut left = logic_or(tokens, interned);
+fn logic_arrow(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let mut left = logic_or(tokens, interned, scope)?;
 
-    while let Some(Token::Arrow) = tokens.peek() {
+    while let Some(TokenKind::Arrow) = peek(tokens) {
         tokens.next();
-        let right = logic_or(tokens, interned)?;
-        left = Ok(Expr::Binary(Box::new(left?), BinOperator::Arrow, Box::new(right)));
+        let right = logic_or(tokens, interned, scope)?;
+        left = Expr::Binary(Box::new(left), BinOperator::Arrow, Box::new(right));
     }
-    left
+    Ok(left)
 }
 
-fn logic_or(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
-    let mut left = logic_and(tokens, interned);
+fn logic_or(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let mut left = logic_and(tokens, interned, scope)?;
 
-    while let Some(Token::Or) = tokens.peek() {
+    while let Some(TokenKind::Or) = peek(tokens) {
         tokens.next();
-        let right = logic_and(tokens, interned)?;
-        left = Ok(Expr::Binary(Box::new(left?), BinOperator::Or, Box::new(right)));
+        let right = logic_and(tokens, interned, scope)?;
+        left = Expr::Binary(Box::new(left), BinOperator::Or, Box::new(right));
     }
-    left
+    Ok(left)
 }
 
-fn logic_and(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
-    let mut left = logic_not(tokens, interned);
+fn logic_and(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let mut left = logic_not(tokens, interned, scope)?;
 
-    while let Some(Token::And) = tokens.peek() {
+    while let Some(TokenKind::And) = peek(tokens) {
         tokens.next();
-        let right = logic_not(tokens, interned)?;
-        left = Ok(Expr::Binary(Box::new(left?), BinOperator::And, Box::new(right)));
+        let right = logic_not(tokens, interned, scope)?;
+        left = Expr::Binary(Box::new(left), BinOperator::And, Box::new(right));
     }
-    left
+    Ok(left)
 }
 
-fn logic_not(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
+fn logic_not(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
     
-    if let Some(Token::Not) = tokens.peek() {
+    if let Some(TokenKind::Not) = peek(tokens) {
         tokens.next();
-        return Ok(Expr::Not(Box::new(logic_not(tokens, interned)?)));
+        return Ok(Expr::Not(Box::new(logic_not(tokens, interned, scope)?)));
     }
-    primary(tokens, interned)
+    primary(tokens, interned, scope)
 }
 
-fn primary(tokens: &mut Peekable<Iter<Token>>, interned: &mut Vec<String>) -> Result<Expr, String> {
-    match tokens.next() {
-        Some(Token::Identifier(s)) => {
-            let index = interned.iter().position(|i| i == s);
-            match index {
-                Some(n) => Ok(Expr::Primary(n)),
-                None => {
-                    interned.push(s.to_string());
-                    Ok(Expr::Primary(interned.len()-1))
-                }
+fn primary(tokens: &mut Tokens, interned: &mut Interner, scope: &mut Scope) -> Result<Expr, Error> {
+    let Some(token) = tokens.next() else {
+        return Err(expected(None, "an expression"));
+    };
+    match &token.kind {
+        TokenKind::Identifier(s) => match scope.definition(s) {
+            // Defined names are spliced in like answers, the tree keeps their precedence
+            Some(expr) => splice(expr, s, token.span, interned, scope),
+            None => Ok(Expr::Primary(interned.intern(s))),
+        },
+        TokenKind::Constant(b) => Ok(Expr::Constant(*b)),
+        TokenKind::Answer(answer) => {
+            match scope.history.get(*answer) {
+                Some(expr) => splice(expr, &answer.to_string(), token.span, interned, scope),
+                None => Err(Error::UnknownAnswer(*answer, token.span)),
             }
         },
-        Some(Token::OpenParen) => {
-            let expr = logic_twin_arrow(tokens, interned)?;
-            if let Some(Token::CloseParen) = tokens.next() {
-                Ok(Expr::Group(Box::new(expr)))
+        TokenKind::Forall | TokenKind::Exists => {
+            let quantifier = if let TokenKind::Forall = token.kind { Quantifier::Forall } else { Quantifier::Exists };
+            let Some(Token { kind: TokenKind::Identifier(s), .. }) = tokens.peek() else {
+                return Err(expected(tokens.next(), "an atom after the quantifier"));
+            };
+            tokens.next();
+            let atom = interned.intern(s);
+            if let Some(TokenKind::Dot) = peek(tokens) {
+                tokens.next();
+                // The body extends as far to the right as possible, the bound atom shadows a definition with its name
+                scope.bound.push(s.clone());
+                let body = logic_twin_arrow(tokens, interned, scope);
+                scope.bound.pop();
+                let body = body?;
+                Ok(Expr::Quantified(quantifier, atom, Box::new(body)))
             } else {
-                Err("Missing closing parenthesis".to_string())
+                Err(expected(tokens.next(), "'.' after the bound atom"))
             }
         },
-        Some(other) => {
-            Err(format!("Unexpected token: {:?}", other))
+        TokenKind::OpenParen => {
+            let expr = logic_twin_arrow(tokens, interned, scope)?;
+            match tokens.next() {
+                Some(Token { kind: TokenKind::CloseParen, .. }) => Ok(Expr::Group(Box::new(expr))),
+                other => Err(expected(other, "')'")),
+            }
         },
-        None => Err("Unexpected token: None".to_string()),
+        _ => Err(expected(Some(token), "an expression")),
+    }
+}
+
+// The free atoms of a spliced expression must stay free, a quantifier around the reference would capture them
+fn splice(expr: &Expr, name: &str, span: Span, interned: &Interner, scope: &Scope) -> Result<Expr, Error> {
+    let captured = runtime::free_atoms(expr).into_iter().find(|n| scope.bound.contains(&interned[*n]));
+    match captured {
+        Some(n) => Err(Error::Captured(name.to_string(), interned[n].to_string(), span)),
+        None => Ok(expr.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::AnswerRef;
+
+    fn parse_str(input: &str, interned: &mut Interner, history: &History) -> Result<Expr, Error> {
+        let mut tokens = Vec::new();
+        lexer::tokenize(&mut tokens, input)?;
+        parse(&mut tokens, interned, history)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn atoms_keep_their_index_across_lines() {
+        let mut interned = Interner::new();
+        let history = History::new();
+        let first = parse_str("q & p", &mut interned, &history).unwrap();
+        let second = parse_str("p & q", &mut interned, &history).unwrap();
+        let (p, q) = (Box::new(Expr::Primary(1)), Box::new(Expr::Primary(0)));
+        assert_eq!(first, Expr::Binary(q.clone(), BinOperator::And, p.clone()));
+        assert_eq!(second, Expr::Binary(p, BinOperator::And, q));
+    }
+
+    #[test]
+    fn answers_are_spliced_as_trees() {
+        let mut interned = Interner::new();
+        let mut history = History::new();
+        let answer = parse_str("a | b", &mut interned, &history).unwrap();
+        history.push(answer.clone());
+        let e = parse_str("$1 & c", &mut interned, &history).unwrap();
+        let c = Expr::Primary(interned.intern("c"));
+        assert_eq!(e, Expr::Binary(Box::new(answer), BinOperator::And, Box::new(c)));
+        assert_eq!(utils::expr_to_string(&e, &interned), "(a | b) & c");
+    }
+
+    #[test]
+    fn unknown_answers_point_at_the_reference() {
+        let mut interned = Interner::new();
+        let err = parse_str("p & ans[-2]", &mut interned, &History::new()).unwrap_err();
+        assert_eq!(err, Error::UnknownAnswer(AnswerRef::Relative(2), Span { start: 4, end: 11 }));
+    }
+
+    #[test]
+    fn parse_errors_are_rendered_under_the_input() {
+        let mut interned = Interner::new();
+        let history = History::new();
+        assert_eq!(parse_line("p & )", &mut interned, &history), Err("Expected an expression, found ')'\n  p & )\n      ^".to_string()));
+        assert_eq!(parse_line("(p | q", &mut interned, &history), Err("Expected ')', found end of input\n  (p | q\n        ^".to_string()));
+        assert_eq!(parse_formula(" a => b = c", &mut interned, &history), Err("Expected a propositional expression, found a => b = c\n   a => b = c\n   ^^^^^^^^^^".to_string()));
+    }
+
+    #[test]
+    fn argument_with_premises() {
+        let mut interned = Interner::new();
+        let e = parse_str("p -> q, p |- q", &mut interned, &History::new()).unwrap();
+        let Expr::Argument(premises, conclusion) = e else { panic!("expected an argument") };
+        assert_eq!(premises.len(), 2);
+        assert_eq!(*conclusion, Expr::Primary(interned.intern("q")));
+    }
+
+    #[test]
+    fn argument_rejects_rule_premises() {
+        let mut interned = Interner::new();
+        for input in ["x := a = b, c |- d", "p => q = r, p |- q", "p => q = r |- q"] {
+            match parse_str(input, &mut interned, &History::new()) {
+                Err(Error::NotAFormula(_, span)) => assert_eq!(span.start, 0),
+                other => panic!("expected an error for {}, found {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn definitions_are_spliced_as_subtrees() {
+        let mut interned = Interner::new();
+        let mut history = History::new();
+        let def = parse_str("a & b", &mut interned, &history).unwrap();
+        history.define("p", def.clone());
+        let e = parse_str("p | q", &mut interned, &history).unwrap();
+        let q = Expr::Primary(interned.intern("q"));
+        assert_eq!(e, Expr::Binary(Box::new(def), BinOperator::Or, Box::new(q)));
+        assert_eq!(utils::expr_to_string(&e, &interned), "a & b | q");
+    }
+
+    #[test]
+    fn bound_atoms_and_rules_shadow_definitions() {
+        let mut interned = Interner::new();
+        let mut history = History::new();
+        let def = parse_str("a & b", &mut interned, &history).unwrap();
+        history.define("p", def);
+        let p = interned.intern("p");
+        let q = interned.intern("q");
+
+        let e = parse_str("forall p. p | q", &mut interned, &history).unwrap();
+        let body = Expr::Binary(Box::new(Expr::Primary(p)), BinOperator::Or, Box::new(Expr::Primary(q)));
+        assert_eq!(e, Expr::Quantified(Quantifier::Forall, p, Box::new(body)));
+
+        let Expr::Binding(name, rule) = parse_str("p := p & q = q & p", &mut interned, &history).unwrap() else { panic!("expected a binding") };
+        assert_eq!(*name, Expr::Primary(p));
+        let Rule::Equivalence(lhs, _) = *rule else { panic!("expected two sides") };
+        assert_eq!(lhs, Expr::Binary(Box::new(Expr::Primary(p)), BinOperator::And, Box::new(Expr::Primary(q))));
+
+        let Expr::Pattern(left, rule) = parse_str("p => p = ~~p", &mut interned, &history).unwrap() else { panic!("expected a pattern") };
+        assert!(matches!(*left, Expr::Binary(..)));
+        assert_eq!(*rule, Rule::Equivalence(Expr::Primary(p), Expr::Not(Box::new(Expr::Not(Box::new(Expr::Primary(p)))))));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{parser::{Expr, BinOperator, Quantifier}, interner::Interner, runtime};
+
+// A literal is an atom with its sign, a clause is a sorted set of literals and the empty clause is a contradiction
+type Literal = (usize, bool);
+type Clause = Vec<Literal>;
+
+// Stops the given-clause loop on inputs whose clause set explodes
+const MAX_CLAUSES: usize = 10000;
+
+pub enum Origin {
+    Premise,
+    NegatedGoal,
+    Resolvent(usize, usize, usize),
+}
+
+pub struct Step {
+    pub clause: Clause,
+    pub origin: Origin,
+}
+
+pub struct Derivation {
+    pub steps: Vec<Step>,
+    pub refutation: Option<usize>,      // the step with the empty clause
+}
+
+fn or_clauses(left: Vec<Clause>, right: Vec<Clause>) -> Vec<Clause> {
+    let mut res = Vec::new();
+    for l in &left {
+        for r in &right {
+            let mut c = l.clone();
+            c.extend(r.iter().copied());
+            res.push(c);
+        }
+    }
+    res
+}
+
+// Clause form of `expr`, or of its negation when `negated` is set, by pushing negations to the atoms
+// and distributing disjunctions over conjunctions
+fn clauses(expr: &Expr, negated: bool) -> Vec<Clause> {
+    use BinOperator::*;
+    match (expr, negated) {
+        (Expr::Group(e), _) => clauses(e, negated),
+        (Expr::Not(e), _) => clauses(e, !negated),
+        (Expr::Primary(n), _) => vec![vec![(*n, !negated)]],
+        (Expr::Constant(b), _) if *b != negated => Vec::new(),
+        (Expr::Constant(_), _) => vec![Vec::new()],
+        (Expr::Binary(l, And, r), false) | (Expr::Binary(l, Or, r), true) => {
+            let mut res = clauses(l, negated);
+            res.extend(clauses(r, negated));
+            res
+        },
+        (Expr::Binary(l, Or, r), false) | (Expr::Binary(l, And, r), true) => or_clauses(clauses(l, negated), clauses(r, negated)),
+        (Expr::Binary(l, Arrow, r), false) => or_clauses(clauses(l, true), clauses(r, false)),
+        (Expr::Binary(l, Arrow, r), true) => {
+            let mut res = clauses(l, false);
+            res.extend(clauses(r, true));
+            res
+        },
+        (Expr::Binary(l, TwinArrow, r), _) => {
+            let forward = Expr::Binary(l.clone(), Arrow, r.clone());
+            let backward = Expr::Binary(r.clone(), Arrow, l.clone());
+            clauses(&Expr::Binary(Box::new(forward), And, Box::new(backward)), negated)
+        },
+        (Expr::Quantified(q, n, e), _) => {
+            let op = if let Quantifier::Forall = q { And } else { Or };
+            let low = runtime::instantiate(e, *n, &Expr::Constant(false));
+            let high = runtime::instantiate(e, *n, &Expr::Constant(true));
+            clauses(&Expr::Binary(Box::new(low), op, Box::new(high)), negated)
+        },
+        _ => Vec::new(),
+    }
+}
+
+// Sorts the literals and drops tautologies such as {p, ~p}
+fn normalize(mut clause: Clause) -> Option<Clause> {
+    clause.sort();
+    clause.dedup();
+    if clause.windows(2).any(|w| w[0].0 == w[1].0) {
+        None
+    } else {
+        Some(clause)
+    }
+}
+
+fn subsumes(c: &Clause, d: &Clause) -> bool {
+    c.iter().all(|l| d.contains(l))
+}
+
+fn resolvents(a: &Clause, b: &Clause) -> Vec<(usize, Clause)> {
+    a.iter()
+        .filter(|(n, sign)| b.contains(&(*n, !sign)))
+        .filter_map(|(n, sign)| {
+            let mut c = a.iter().filter(|l| **l != (*n, *sign)).copied().collect::<Clause>();
+            c.extend(b.iter().filter(|l| **l != (*n, !sign)).copied());
+            normalize(c).map(|c| (*n, c))
+        })
+        .collect()
+}
+
+// Given-clause loop: the shortest unprocessed clause is resolved with every processed clause,
+// clauses subsumed by a kept clause are discarded in both directions
+pub fn refute(premises: &[Expr], goal: &Expr) -> Result<Derivation, String> {
+    let mut steps: Vec<Step> = Vec::new();
+    let mut unprocessed: Vec<usize> = Vec::new();
+    let inputs = premises
+        .iter()
+        .flat_map(|p| clauses(p, false).into_iter().map(|c| (c, Origin::Premise)))
+        .chain(clauses(goal, true).into_iter().map(|c| (c, Origin::NegatedGoal)));
+    for (clause, origin) in inputs {
+        if let Some(clause) = normalize(clause) {
+            if !steps.iter().any(|s| s.clause == clause) {
+                unprocessed.push(steps.len());
+                steps.push(Step { clause, origin });
+            }
+        }
+    }
+
+    let mut processed: Vec<usize> = Vec::new();
+    while let Some(pos) = (0..unprocessed.len()).min_by_key(|i| steps[unprocessed[*i]].clause.len()) {
+        let given = unprocessed.remove(pos);
+        if steps[given].clause.is_empty() {
+            return Ok(Derivation { steps, refutation: Some(given) });
+        }
+        if processed.iter().any(|p| subsumes(&steps[*p].clause, &steps[given].clause)) {
+            continue;
+        }
+        processed.retain(|p| !subsumes(&steps[given].clause, &steps[*p].clause));
+        unprocessed.retain(|p| !subsumes(&steps[given].clause, &steps[*p].clause));
+        processed.push(given);
+
+        for other in processed.clone() {
+            for (atom, clause) in resolvents(&steps[given].clause, &steps[other].clause) {
+                if processed.iter().chain(unprocessed.iter()).any(|p| subsumes(&steps[*p].clause, &clause)) {
+                    continue;
+                }
+                let empty = clause.is_empty();
+                unprocessed.push(steps.len());
+                steps.push(Step { clause, origin: Origin::Resolvent(other, given, atom) });
+                if empty {
+                    let refutation = Some(steps.len() - 1);
+                    return Ok(Derivation { steps, refutation });
+                }
+                if steps.len() > MAX_CLAUSES {
+                    return Err(format!("Gave up after {} clauses", MAX_CLAUSES));
+                }
+            }
+        }
+    }
+    Ok(Derivation { steps, refutation: None })
+}
+
+pub fn clause_to_string(clause: &Clause, interned: &Interner) -> String {
+    let literals = clause
+        .iter()
+        .map(|(n, sign)| format!("{}{}", if *sign { "" } else { "~" }, interned[*n]))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", literals.join(", "))
+}
+
+impl Derivation {
+    // Steps that the clause of step `last` was derived from, in the order they were found
+    fn ancestors(&self, last: usize) -> Vec<usize> {
+        let mut used = vec![false; self.steps.len()];
+        let mut stack = vec![last];
+        while let Some(i) = stack.pop() {
+            if !used[i] {
+                used[i] = true;
+                if let Origin::Resolvent(a, b, _) = self.steps[i].origin {
+                    stack.push(a);
+                    stack.push(b);
+                }
+            }
+        }
+        (0..self.steps.len()).filter(|i| used[*i]).collect()
+    }
+
+    // Shown steps are numbered consecutively, references use the same numbers
+    pub fn print(&self, interned: &Interner) {
+        let shown = match self.refutation {
+            Some(last) => self.ancestors(last),
+            None => (0..self.steps.len()).collect(),
+        };
+        let mut number = vec![0; self.steps.len()];
+        for (k, i) in shown.iter().enumerate() {
+            number[*i] = k + 1;
+        }
+        let width = shown.iter().map(|i| clause_to_string(&self.steps[*i].clause, interned).len()).max().unwrap_or(0);
+        for i in shown {
+            let step = &self.steps[i];
+            let reason = match step.origin {
+                Origin::Premise => "premise".to_string(),
+                Origin::NegatedGoal => "negated goal".to_string(),
+                Origin::Resolvent(a, b, atom) => format!("resolve {}, {} on {}", number[a], number[b], interned[atom]),
+            };
+            println!("{:>3}. {:<width$}   {}", number[i], clause_to_string(&step.clause, interned), reason, width = width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    fn refute_str(premises: &[&str], goal: &str) -> Derivation {
+        let mut interned = Interner::new();
+        let history = History::new();
+        let premises = premises.iter().map(|p| parser::parse_formula(p, &mut interned, &history).unwrap()).collect::<Vec<_>>();
+        let goal = parser::parse_formula(goal, &mut interned, &history).unwrap();
+        refute(&premises, &goal).unwrap()
+    }
+
+    #[test]
+    fn modus_ponens_is_refuted() {
+        let d = refute_str(&["p -> q", "p"], "q");
+        let last = d.refutation.expect("the empty clause is derived");
+        assert!(d.steps[last].clause.is_empty());
+        assert!(d.ancestors(last).len() >= 3);
+    }
+
+    #[test]
+    fn empty_input_clause_is_the_refutation() {
+        let d = refute_str(&["0", "p"], "q");
+        let last = d.refutation.expect("the premise 0 is the empty clause");
+        assert!(d.steps[last].clause.is_empty());
+        assert!(matches!(d.steps[last].origin, Origin::Premise));
+        assert_eq!(d.ancestors(last), vec![last]);
+    }
+
+    #[test]
+    fn saturates_when_the_goal_does_not_follow() {
+        assert!(refute_str(&["p | q"], "p").refutation.is_none());
+    }
+
+    #[test]
+    fn tautologies_are_dropped() {
+        assert_eq!(normalize(vec![(0, true), (0, false)]), None);
+        assert_eq!(normalize(vec![(1, true), (0, false), (1, true)]), Some(vec![(0, false), (1, true)]));
+    }
+}
@@ -1,45 +1,59 @@
 use std::{collections::HashMap};
-use crate::{parser::{Expr, Rule, BinOperator}, utils};
+use crate::{parser::{Expr, Rule}, interner::Interner, logic::Logic, error::Error, utils};
 
 #[derive(Debug)]
-pub struct Table {
+pub struct Table<'a> {
     map: HashMap<Expr, Vec<usize>>,
     atoms: Vec<Expr>,
-    pub interned: Vec<String>,
+    pub interned: &'a Interner,
+    result: Vec<usize>,
     rows: usize,
     is_num_symbols: bool,
+    logic: Logic,
 }
 
-impl Table {
-    pub fn new(interned: &Vec<String>, is_num_symbols: &bool) -> Self {
+impl<'a> Table<'a> {
+    pub fn new(interned: &'a Interner, is_num_symbols: &bool) -> Self {
+        Table::with_logic(interned, is_num_symbols, Logic::Classical)
+    }
+
+    pub fn with_logic(interned: &'a Interner, is_num_symbols: &bool, logic: Logic) -> Self {
         Self { 
             map: HashMap::new(),
             atoms: Vec::new(),
-            interned: interned.clone(),
+            interned,
+            result: Vec::new(),
             rows: 0,
-            is_num_symbols: is_num_symbols.clone(),
+            is_num_symbols: *is_num_symbols,
+            logic,
         }
     }
 
     fn fill_symbols(&mut self, expr: &Expr){
         match expr {
             Expr::Binary(left, _, right) => {
-                self.fill_symbols(&*left);
-                self.fill_symbols(&*right);
+                self.fill_symbols(left);
+                self.fill_symbols(right);
             },
             Expr::Not(e) => {
-                self.fill_symbols(&*e);
+                self.fill_symbols(e);
             },
             Expr::Group(e) => {
-                self.fill_symbols(&*e);
+                self.fill_symbols(e);
             },
             e @ Expr::Primary(_) => {
-                if let None = self.map.get(&e) {
+                if !self.map.contains_key(e) {
                     self.map.insert(e.clone(), Vec::new());
                     self.atoms.push(e.clone());
                 }
             },
-            other => println!("Unreachable: found {}", utils::expr_to_string(other, &self.interned))
+            Expr::Quantified(_, n, e) => {
+                for atom in free_atoms(e).into_iter().filter(|a| a != n) {
+                    self.fill_symbols(&Expr::Primary(atom));
+                }
+            },
+            Expr::Constant(_) => {},
+            other => println!("Unreachable: found {}", utils::expr_to_string(other, self.interned))
         }
     }
 
@@ -54,34 +68,49 @@ impl Table {
     fn eval(&mut self, expr: &Expr) -> Vec<usize> {
         match expr {
             e @ Expr::Binary(l, op, r) => {
-                let left = self.eval(&*l);
-                let right = self.eval(&*r);
+                let left = self.eval(l);
+                let right = self.eval(r);
                 
-                let res = match op {
-                    BinOperator::And => Table::zipped_operation(left, right, |(a, b)| a & b),
-                    BinOperator::Or => Table::zipped_operation(left, right, |(a, b)| a | b),
-                    BinOperator::Arrow => Table::zipped_operation(left, right, |(a, b)| if a.eq(&1) && b.eq(&0) { 0 } else { 1 }),
-                    BinOperator::TwinArrow => Table::zipped_operation(left, right, |(a, b)| a.eq(b) as usize),
-                };
+                let logic = self.logic;
+                let res = Table::zipped_operation(left, right, |(a, b)| logic.binary(*op, *a, *b));
                 
-                if let None = self.map.get(&e) {
+                if !self.map.contains_key(e) {
                     self.map.insert(e.clone(), res.clone());
                 }
                 res
             },
             e @ Expr::Not(inner) => {
-                let res = self.eval(&*inner)
+                let res = self.eval(inner)
                                           .iter()
-                                          .map(|x| if x.eq(&0) { 1 } else { 0 })
+                                          .map(|x| self.logic.not(*x))
                                           .collect::<Vec<usize>>();
-                if let None = self.map.get(&e) {
+                if !self.map.contains_key(e) {
                     self.map.insert(e.clone(), res.clone());
                 }
                 res
             },
             e @ Expr::Group(inner) => {
-                let res = self.eval(&*inner);
-                if let None = self.map.get(&e) {
+                let res = self.eval(inner);
+                if !self.map.contains_key(e) {
+                    self.map.insert(e.clone(), res.clone());
+                }
+                res
+            },
+            // The bound atom takes every truth value for every row, the columns of the body are not kept
+            e @ Expr::Quantified(q, n, inner) => {
+                let saved = self.map.clone();
+                let mut columns = Vec::new();
+                for v in 0..self.logic.values() {
+                    self.map = saved.clone();
+                    self.map.insert(Expr::Primary(*n), vec![v; self.rows]);
+                    columns.push(self.eval(inner));
+                }
+                self.map = saved;
+
+                let res = (0..self.rows)
+                    .map(|i| self.logic.quantify(*q, &columns.iter().map(|c| c[i]).collect::<Vec<_>>()))
+                    .collect::<Vec<usize>>();
+                if !self.map.contains_key(e) {
                     self.map.insert(e.clone(), res.clone());
                 }
                 res
@@ -89,46 +118,76 @@ impl Table {
             e @ Expr::Primary(_) => {
                 self.map.get(e).unwrap().to_vec()
             },
+            e @ Expr::Constant(b) => {
+                let res = vec![self.logic.constant(*b); self.rows];
+                if !self.map.contains_key(e) {
+                    self.map.insert(e.clone(), res.clone());
+                }
+                res
+            },
             _ => panic!("Unreachable eval"),
         }
     }
 
-    pub fn generate_truthtable(&mut self, expr: Expr){
-        self.fill_symbols(&expr);
-
-        let count = self.map.len();
-        self.rows = (2 as usize).pow(count as u32);   
+    // Row i assigns digit j of i in base `values` to atom j, for two values this is bit j
+    fn assign_atoms(&mut self) -> Result<(), String> {
+        let values = self.logic.values();
+        let count = self.atoms.len();
+        self.rows = values.checked_pow(count as u32).ok_or(format!("Too many atoms for a truth table, found {}", count))?;
         
-        self.atoms.sort_by(|a, b| a.cmp(&b));
+        self.atoms.sort();
         for (j, e) in self.atoms.iter().enumerate() {
-            let mut entry: Vec<usize> = Vec::new();
-            for i in 0..self.rows{
-                let k = (2 as usize).pow((j+1) as u32);
-                if i % k + 1 > k / 2 {
-                    entry.push(1);
-                } else {
-                    entry.push(0);
-                }
-            }
+            let k = values.pow(j as u32);
+            let entry = (0..self.rows).map(|i| (i / k) % values).collect::<Vec<usize>>();
             self.map.insert(e.clone(), entry);
         }
+        Ok(())
+    }
 
-        self.eval(&expr);
-        self.map.retain(|k, _| {
-            if let Expr::Group(_) = k {
-                false
-            } else {
-                true
-            }
-        });
+    pub fn generate_truthtable(&mut self, expr: Expr) -> Result<(), String> {
+        self.fill_symbols(&expr);
+        self.assign_atoms()?;
+
+        self.result = self.eval(&expr);
+        self.map.retain(|k, _| !matches!(k, Expr::Group(_)));
+        Ok(())
+    }
+
+    // Result columns of several expressions over the same rows, nothing is kept for printing
+    pub fn evaluate_all(&mut self, exprs: &[Expr]) -> Result<Vec<Vec<usize>>, String> {
+        for e in exprs {
+            self.fill_symbols(e);
+        }
+        self.assign_atoms()?;
+        Ok(exprs.iter().map(|e| self.eval(e)).collect())
+    }
+
+    pub fn symbol(&self, v: usize) -> char {
+        if self.is_num_symbols && self.logic.values() == 2 {
+            char::from_digit(v as u32, 10).unwrap_or_default()
+        } else {
+            self.logic.symbol(v)
+        }
+    }
+
+    pub fn atoms(&self) -> &[Expr] {
+        &self.atoms
+    }
 
+    pub fn result(&self) -> &[usize] {
+        &self.result
+    }
+
+    // Only the top value is designated, so e.g. K3 has no tautologies
+    pub fn is_tautology(&self) -> bool {
+        self.result.iter().all(|x| *x == self.logic.top())
     }
 
     fn sort(&self) -> Vec<(&Expr, &Vec<usize>)> {
         let mut res = self.map
             .iter()
             .collect::<Vec<_>>();
-        res.sort_by(|x, y| y.0.cmp(&x.0));
+        res.sort_by(|x, y| y.0.cmp(x.0));
         res
     }
 
@@ -137,7 +196,7 @@ impl Table {
         let mut headers: Vec<String> = Vec::new();
         let list = self.sort();
         for (expr, _) in  &list {
-            let head = format!("[ {} ] ", utils::expr_to_string(expr, &self.interned));
+            let head = format!("[ {} ] ", utils::expr_to_string(expr, self.interned));
             total += head.len();
             headers.push(head);
         }
@@ -156,21 +215,15 @@ impl Table {
         for i in 0..self.rows {
             for (j, head) in headers.iter().enumerate() {
                 let len = head.len();
-                let sym = if self.is_num_symbols {
-                    char::from_digit(list[j].1[i] as u32, 10) 
-                } else if list[j].1[i] == 1 {
-                    Some('T')
-                } else {
-                    Some('F')
-                };
+                let sym = self.symbol(list[j].1[i]);
 
                 if len % 2 == 0 {
                     print!("|{: <1$}", "", len/2-2);
-                    print!("{}", sym.unwrap_or_default());
+                    print!("{}", sym);
                     print!("{: <1$}| ", "", len/2-2);
                 } else {
                     print!("|{: <1$}", "", len/2-2);
-                    print!("{}", sym.unwrap_or_default());
+                    print!("{}", sym);
                     print!("{: <1$}| ", "", len/2-1);
                 }
             }
@@ -180,112 +233,370 @@ impl Table {
     }
 }
 
-pub fn match_patterns(expr: &Expr, rule: Rule, interned: &Vec<String>, rule_bindings: &HashMap<String, Rule>) -> Result<String, String> {
-    let result = match rule {
-        Rule::Equivalence(lhs, rhs) => {
-            let mut patterns: HashMap<Expr, Expr> = HashMap::new();
-            match traverse_and_match(expr, &lhs, &mut patterns) {
-                Ok(()) => subsitute_in(&rhs, &mut patterns),
-                Err(_) => {
-                    let mut patterns: HashMap<Expr, Expr> = HashMap::new();
-                    match traverse_and_match(expr, &rhs, &mut patterns) {
-                        Ok(()) => subsitute_in(&lhs, &mut patterns),
-                        Err(s) => Err(s),
-                    } 
-                }
-            }
+pub fn match_patterns(expr: &Expr, rule: Rule, interned: &mut Interner, rule_bindings: &HashMap<String, Rule>) -> Result<Expr, Error> {
+    let (lhs, rhs) = match rule {
+        Rule::Equivalence(lhs, rhs) => (lhs, rhs),
+        Rule::RuleId(n) => match rule_bindings.get(&interned[n]) {
+            Some(Rule::Equivalence(lhs, rhs)) => (lhs.clone(), rhs.clone()),
+            _ => return Err(Error::UndefinedRule(interned[n].clone())),
         },
-        Rule::RuleId(n) => {
-            let rule_maybe = rule_bindings.get(&interned[n]);
-            if let Some(Rule::Equivalence(lhs, rhs)) = rule_maybe {
-                let mut patterns: HashMap<Expr, Expr> = HashMap::new();
-                match traverse_and_match(expr, &lhs, &mut patterns) {
-                    Ok(()) => subsitute_in(&rhs, &mut patterns),
-                    Err(_) => {
-                        let mut patterns: HashMap<Expr, Expr> = HashMap::new();
-                        match traverse_and_match(expr, &rhs, &mut patterns) {
-                            Ok(()) => subsitute_in(&lhs, &mut patterns),
-                            Err(s) => Err(s),
-                        } 
-                    }
-                }
-            } else {
-                return Err("Undefined rule used".to_string());
-            }
-        }
     };
-
-    match result {
-        Ok(e) => Ok(utils::expr_to_string(&e, interned)),
-        Err(s) => Err(s),
+    match match_scoped(expr, &lhs) {
+        Ok((mut patterns, scopes)) => substitute(&rhs, &mut patterns, &scopes, &mut Vec::new(), Some(interned)),
+        Err(_) => {
+            let (mut patterns, scopes) = match_scoped(expr, &rhs).map_err(|_| Error::NoMatch)?;
+            substitute(&lhs, &mut patterns, &scopes, &mut Vec::new(), Some(interned))
+        }
     }
 }
 
-fn traverse_and_match(expr: &Expr, lhs: &Expr, patterns: &mut HashMap<Expr, Expr>) -> Result<(), String>{
+// For every pattern variable, the atoms bound by quantifiers around its match that occur in the matched expression
+type Scopes = HashMap<Expr, Vec<usize>>;
+
+fn match_scoped(expr: &Expr, lhs: &Expr) -> Result<(HashMap<Expr, Expr>, Scopes), Error> {
+    let mut patterns: HashMap<Expr, Expr> = HashMap::new();
+    let mut scopes = Scopes::new();
+    traverse_and_match(expr, lhs, &mut patterns, &mut Vec::new(), &mut scopes)?;
+    Ok((patterns, scopes))
+}
+
+fn traverse_and_match(expr: &Expr, lhs: &Expr, patterns: &mut HashMap<Expr, Expr>, scope: &mut Vec<usize>, scopes: &mut Scopes) -> Result<(), Error>{
+    // Parentheses only fix the shape of the tree, so they are looked through on both sides
+    if let (Expr::Binary(..) | Expr::Not(_) | Expr::Constant(_) | Expr::Quantified(..), Expr::Group(e)) = (lhs, expr) {
+        return traverse_and_match(e, lhs, patterns, scope, scopes);
+    }
     match lhs {
         Expr::Binary(pat_left, pat_op, pat_right) => {
             if let Expr::Binary(e_left, e_op, e_right) = expr {
                 if pat_op == e_op {
-                    if let Ok(()) = traverse_and_match(&*e_left, &*pat_left, patterns) {
-                        traverse_and_match(&*e_right, &*pat_right, patterns)
+                    if let Ok(()) = traverse_and_match(e_left, pat_left, patterns, scope, scopes) {
+                        traverse_and_match(e_right, pat_right, patterns, scope, scopes)
                     } else {
-                        Err(format!("Expression does not match binary pattern"))
+                        Err(Error::NoMatch)
                     }
                 } else {
-                    Err("Expression does not match pattern".to_string())
+                    Err(Error::NoMatch)
                 }
             } else {
-                Err(format!("Expression does not match pattern: {:?}", expr))
+                Err(Error::NoMatch)
             }
         },
         Expr::Not(pat_e) => {
             if let Expr::Not(e) = expr {
-                traverse_and_match(&*e, &*pat_e, patterns)
+                traverse_and_match(e, pat_e, patterns, scope, scopes)
             } else {
-                Err(format!("Expression does not match pattern: {:?}", expr))
+                Err(Error::NoMatch)
             }
         },
-        Expr::Group(pat_e) => {
-            if let Expr::Group(e) = expr {
-                traverse_and_match(&*e, &*pat_e, patterns)
-            } else {
-                Err(format!("Expression does not match pattern: {:?}", expr))
+        Expr::Group(pat_e) => traverse_and_match(expr, pat_e, patterns, scope, scopes),
+        // The bound atom of the pattern is a pattern variable that can only match the bound atom of the expression
+        Expr::Quantified(pat_q, pat_n, pat_e) => {
+            let Expr::Quantified(q, n, e) = expr else {
+                return Err(Error::NoMatch);
+            };
+            if pat_q != q {
+                return Err(Error::NoMatch);
+            }
+            match patterns.get(&Expr::Primary(*pat_n)) {
+                Some(Expr::Primary(bound)) if bound == n => {},
+                Some(_) => return Err(Error::NoMatch),
+                None => {
+                    patterns.insert(Expr::Primary(*pat_n), Expr::Primary(*n));
+                    scopes.insert(Expr::Primary(*pat_n), vec![*n]);
+                },
             }
+            scope.push(*n);
+            let res = traverse_and_match(e, pat_e, patterns, scope, scopes);
+            scope.pop();
+            res
         },
         Expr::Primary(_) => {
-            if let None = patterns.get(lhs) {
-                patterns.insert(lhs.clone(), expr.clone());
+            match patterns.get(lhs) {
+                Some(bound) if strip_groups(bound) != strip_groups(expr) => 
+                    Err(Error::NoMatch),
+                Some(_) => Ok(()),
+                None => {
+                    patterns.insert(lhs.clone(), expr.clone());
+                    let bound = free_atoms(expr).into_iter().filter(|a| scope.contains(a)).collect();
+                    scopes.insert(lhs.clone(), bound);
+                    Ok(())
+                }
             }
-            Ok(())
         },
+        Expr::Constant(_) if lhs == expr => Ok(()),
         _ => {
-            Err(format!("Expression does not match pattern: {:?}", expr))
+            Err(Error::NoMatch)
         }
     }
 }
 
-fn subsitute_in(expr: &Expr, patterns: &mut HashMap<Expr, Expr>) -> Result<Expr, String> {
+pub fn subsitute_in(expr: &Expr, patterns: &mut HashMap<Expr, Expr>) -> Result<Expr, Error> {
+    substitute(expr, patterns, &Scopes::new(), &mut Vec::new(), None)
+}
+
+// `scope` holds the quantifiers around the current position as (atom bound in the matched expression, atom in the result),
+// which differ when a quantifier is renamed to avoid capturing a free atom
+fn substitute(expr: &Expr, patterns: &mut HashMap<Expr, Expr>, scopes: &Scopes, scope: &mut Vec<(usize, usize)>, mut interned: Option<&mut Interner>) -> Result<Expr, Error> {
     match expr {
         Expr::Binary(l, op, r) => {
-            let left = subsitute_in(&*l, patterns)?;
-            let right = subsitute_in(&*r, patterns)?;
+            let left = substitute(l, patterns, scopes, scope, interned.as_deref_mut())?;
+            let right = substitute(r, patterns, scopes, scope, interned)?;
             Ok(Expr::Binary(Box::new(left), *op, Box::new(right)))
         },
         Expr::Not(e) => {
-            let res = subsitute_in(&*e, patterns)?;
+            let res = substitute(e, patterns, scopes, scope, interned)?;
             Ok(Expr::Not(Box::new(res)))
         },
         Expr::Group(e) => {
-            let res = subsitute_in(&*e, patterns)?;
+            let res = substitute(e, patterns, scopes, scope, interned)?;
             Ok(Expr::Group(Box::new(res)))
         },
+        Expr::Quantified(q, n, e) => {
+            let Some(Expr::Primary(atom)) = patterns.get(&Expr::Primary(*n)).cloned() else {
+                return Err(Error::UnboundQuantifier);
+            };
+            // A free occurrence of the atom in the body that was not bound by this quantifier before would be captured
+            let captures = free_atoms(e).iter().any(|v| match patterns.get(&Expr::Primary(*v)) {
+                Some(value) => free_atoms(value).contains(&atom)
+                    && !scopes.get(&Expr::Primary(*v)).is_some_and(|bound| bound.contains(&atom)),
+                None => false,
+            });
+            let renamed = match (captures, interned.as_deref_mut()) {
+                (false, _) => atom,
+                (true, Some(interned)) => fresh_atom(atom, patterns, interned),
+                (true, None) => return Err(Error::Capture(*q)),
+            };
+            scope.push((atom, renamed));
+            let res = substitute(e, patterns, scopes, scope, interned);
+            scope.pop();
+            Ok(Expr::Quantified(*q, renamed, Box::new(res?)))
+        },
+        Expr::Constant(_) => Ok(expr.clone()),
         Expr::Primary(_) => {
-            if let Some(v) = patterns.get(&expr) {
-                Ok(v.clone())
-            } else {
-                Err("Pattern could not be found in expression or rule".to_string())
+            let Some(v) = patterns.get(expr) else {
+                return Err(Error::UnboundVariable);
+            };
+            let mut res = v.clone();
+            for bound in scopes.get(expr).map_or(&[][..], |b| b.as_slice()) {
+                match scope.iter().rev().find(|(atom, _)| atom == bound) {
+                    Some((_, renamed)) if renamed != bound => res = instantiate(&res, *bound, &Expr::Primary(*renamed)),
+                    Some(_) => {},
+                    None => return Err(Error::Escape),
+                }
+            }
+            Ok(res)
+        },
+        _ => Err(Error::NoMatch)
+    }
+}
+
+// A new name for `atom` that does not occur in any of the matched expressions
+fn fresh_atom(atom: usize, patterns: &HashMap<Expr, Expr>, interned: &mut Interner) -> usize {
+    let mut used = Vec::new();
+    for value in patterns.values() {
+        all_atoms(value, &mut used);
+    }
+    let mut name = interned[atom].clone();
+    loop {
+        name.push('x');
+        let n = interned.intern(&name);
+        if !used.contains(&n) {
+            return n;
+        }
+    }
+}
+
+fn all_atoms(expr: &Expr, atoms: &mut Vec<usize>) {
+    match expr {
+        Expr::Binary(l, _, r) => {
+            all_atoms(l, atoms);
+            all_atoms(r, atoms);
+        },
+        Expr::Not(e) | Expr::Group(e) => all_atoms(e, atoms),
+        Expr::Quantified(_, n, e) => {
+            atoms.push(*n);
+            all_atoms(e, atoms);
+        },
+        Expr::Primary(n) => atoms.push(*n),
+        _ => {},
+    }
+}
+
+fn collect_free(expr: &Expr, bound: &mut Vec<usize>, atoms: &mut Vec<usize>) {
+    match expr {
+        Expr::Binary(l, _, r) => {
+            collect_free(l, bound, atoms);
+            collect_free(r, bound, atoms);
+        },
+        Expr::Not(e) | Expr::Group(e) => collect_free(e, bound, atoms),
+        Expr::Quantified(_, n, e) => {
+            bound.push(*n);
+            collect_free(e, bound, atoms);
+            bound.pop();
+        },
+        Expr::Primary(n) if !bound.contains(n) && !atoms.contains(n) => atoms.push(*n),
+        _ => {},
+    }
+}
+
+// Atoms that are not bound by a quantifier, in order of first occurrence
+pub fn free_atoms(expr: &Expr) -> Vec<usize> {
+    let mut atoms = Vec::new();
+    collect_free(expr, &mut Vec::new(), &mut atoms);
+    atoms
+}
+
+// Replaces the free occurrences of `atom` by `value`, which must not contain atoms bound in `expr`
+pub fn instantiate(expr: &Expr, atom: usize, value: &Expr) -> Expr {
+    match expr {
+        Expr::Binary(l, op, r) => Expr::Binary(Box::new(instantiate(l, atom, value)), *op, Box::new(instantiate(r, atom, value))),
+        Expr::Not(e) => Expr::Not(Box::new(instantiate(e, atom, value))),
+        Expr::Group(e) => Expr::Group(Box::new(instantiate(e, atom, value))),
+        Expr::Quantified(q, n, e) if *n != atom => Expr::Quantified(*q, *n, Box::new(instantiate(e, atom, value))),
+        Expr::Primary(n) if *n == atom => value.clone(),
+        other => other.clone(),
+    }
+}
+
+pub fn not(expr: &Expr) -> Expr {
+    Expr::Not(Box::new(expr.clone()))
+}
+
+// The body of a quantifier over `atom` with the atom false and with the atom true
+pub fn instances(expr: &Expr, atom: usize) -> (Expr, Expr) {
+    (instantiate(expr, atom, &Expr::Constant(false)), instantiate(expr, atom, &Expr::Constant(true)))
+}
+
+pub fn strip_groups(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Binary(l, op, r) => Expr::Binary(Box::new(strip_groups(l)), *op, Box::new(strip_groups(r))),
+        Expr::Not(e) => Expr::Not(Box::new(strip_groups(e))),
+        Expr::Group(e) => strip_groups(e),
+        Expr::Quantified(q, n, e) => Expr::Quantified(*q, *n, Box::new(strip_groups(e))),
+        other => other.clone(),
+    }
+}
+
+pub fn is_equivalent(left: &Expr, right: &Expr, interned: &Interner) -> Result<bool, String> {
+    is_equivalent_in(left, right, interned, Logic::Classical)
+}
+
+// Both sides take the same truth value under every assignment
+pub fn is_equivalent_in(left: &Expr, right: &Expr, interned: &Interner, logic: Logic) -> Result<bool, String> {
+    let mut table = Table::with_logic(interned, &true, logic);
+    let columns = table.evaluate_all(&[left.clone(), right.clone()])?;
+    Ok(columns[0] == columns[1])
+}
+
+pub fn match_pattern(expr: &Expr, lhs: &Expr) -> Result<HashMap<Expr, Expr>, Error> {
+    match_scoped(expr, lhs).map(|(patterns, _)| patterns)
+}
+
+// Applies `lhs = rhs` once, from left to right, at every position of `expr` where `lhs` matches
+pub fn rewrite_subterms(expr: &Expr, lhs: &Expr, rhs: &Expr) -> Vec<Expr> {
+    let mut results = Vec::new();
+    if let Ok((mut patterns, scopes)) = match_scoped(expr, lhs) {
+        if let Ok(e) = substitute(rhs, &mut patterns, &scopes, &mut Vec::new(), None) {
+            results.push(e);
+        }
+    }
+
+    match expr {
+        Expr::Binary(l, op, r) => {
+            for e in rewrite_subterms(l, lhs, rhs) {
+                results.push(Expr::Binary(Box::new(e), *op, r.clone()));
+            }
+            for e in rewrite_subterms(r, lhs, rhs) {
+                results.push(Expr::Binary(l.clone(), *op, Box::new(e)));
+            }
+        },
+        Expr::Not(inner) => {
+            for e in rewrite_subterms(inner, lhs, rhs) {
+                results.push(Expr::Not(Box::new(e)));
+            }
+        },
+        Expr::Group(inner) => {
+            for e in rewrite_subterms(inner, lhs, rhs) {
+                results.push(Expr::Group(Box::new(e)));
+            }
+        },
+        Expr::Quantified(q, n, inner) => {
+            for e in rewrite_subterms(inner, lhs, rhs) {
+                results.push(Expr::Quantified(*q, *n, Box::new(e)));
             }
         },
-        _ => Err("Unreachable".to_string())
+        _ => {},
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    fn parse(input: &str, interned: &mut Interner) -> Expr {
+        parser::parse_formula(input, interned, &History::new()).unwrap()
+    }
+
+    fn equivalent(left: &str, right: &str) -> bool {
+        let mut interned = Interner::new();
+        let (left, right) = (parse(left, &mut interned), parse(right, &mut interned));
+        is_equivalent(&left, &right, &interned).unwrap()
+    }
+
+    fn apply(rule: &str, input: &str) -> Result<String, Error> {
+        let mut interned = Interner::new();
+        let (lhs, rhs) = parser::parse_rule(rule, &mut interned).unwrap();
+        let expr = parse(input, &mut interned);
+        let res = match_patterns(&expr, Rule::Equivalence(lhs, rhs), &mut interned, &HashMap::new())?;
+        Ok(utils::expr_to_string(&res, &interned))
+    }
+
+    #[test]
+    fn quantifiers_range_over_both_values() {
+        assert!(equivalent("forall p. p | q", "q"));
+        assert!(equivalent("exists p. p & q", "q"));
+        assert!(equivalent("forall p. p -> p", "1"));
+        assert!(equivalent("exists p. forall q. p | q", "1"));
+        assert!(equivalent("forall q. exists p. p <-> q", "1"));
+        assert!(!equivalent("forall p. p", "1"));
+    }
+
+    #[test]
+    fn bound_atoms_are_not_free() {
+        let mut interned = Interner::new();
+        let expr = parse("q & (forall p. p | r) | p", &mut interned);
+        let names = free_atoms(&expr).iter().map(|a| interned[*a].clone()).collect::<Vec<_>>();
+        assert_eq!(names, ["q", "r", "p"]);
+        let p = interned.intern("p");
+        let res = instantiate(&expr, p, &Expr::Constant(true));
+        assert_eq!(utils::expr_to_string(&res, &interned), "q & (forall p. p | r) | 1");
+        let (low, high) = instances(&expr, p);
+        assert_eq!((utils::expr_to_string(&low, &interned), high), ("q & (forall p. p | r) | 0".to_string(), res));
+    }
+
+    #[test]
+    fn rules_move_atoms_in_and_out_of_quantifiers() {
+        let pull = "pull := forall x. (a & b) = a & (forall x. b)";
+        assert_eq!(apply(pull, "forall p. q & p"), Ok("q & (forall p. p)".to_string()));
+        assert_eq!(apply(pull, "forall p. p & q"), Err(Error::Escape));
+    }
+
+    #[test]
+    fn substitution_renames_to_avoid_capture() {
+        let push = "push := a & (forall x. b) = forall x. (a & b)";
+        assert_eq!(apply(push, "p & (forall p. q)"), Ok("forall px. (p & q)".to_string()));
+        assert_eq!(apply(push, "r & (forall p. p)"), Ok("forall p. (r & p)".to_string()));
+    }
+
+    #[test]
+    fn too_many_atoms_for_a_truth_table_is_an_error() {
+        let mut interned = Interner::new();
+        let names = (1..=64).map(|i| "a".repeat(i)).collect::<Vec<_>>();
+        let expr = parse(&names.join(" & "), &mut interned);
+        let mut table = Table::new(&interned, &true);
+        assert!(table.generate_truthtable(expr.clone()).is_err());
+        assert!(is_equivalent(&expr, &expr, &interned).is_err());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,333 @@
+use crate::{parser::{Expr, BinOperator, Quantifier}, interner::Interner, runtime::{self, instances}, utils};
+
+#[derive(Clone)]
+pub struct Sequent {
+    left: Vec<Expr>,
+    right: Vec<Expr>,
+}
+
+// A leaf without a rule is an unprovable sequent of atoms
+pub struct Proof {
+    sequent: Sequent,
+    rule: Option<String>,
+    premises: Vec<Proof>,
+}
+
+impl Sequent {
+    pub fn new(premises: &[Expr], goal: &Expr) -> Self {
+        Self {
+            left: premises.iter().map(runtime::strip_groups).collect(),
+            right: vec![runtime::strip_groups(goal)],
+        }
+    }
+
+    fn is_axiom(&self) -> bool {
+        self.left.iter().any(|f| self.right.contains(f))
+            || self.left.contains(&Expr::Constant(false))
+            || self.right.contains(&Expr::Constant(true))
+    }
+
+    fn with(&self, side_left: bool, i: usize, left: Vec<Expr>, right: Vec<Expr>) -> Sequent {
+        let mut s = self.clone();
+        if side_left {
+            s.left.remove(i);
+        } else {
+            s.right.remove(i);
+        }
+        s.left.extend(left);
+        s.right.extend(right);
+        s
+    }
+
+    pub fn to_string(&self, interned: &Interner) -> String {
+        let side = |fs: &[Expr]| fs.iter().map(|f| utils::expr_to_string(f, interned)).collect::<Vec<_>>().join(", ");
+        format!("{} |- {}", side(&self.left), side(&self.right)).trim().to_string()
+    }
+}
+
+fn is_atomic(expr: &Expr) -> bool {
+    matches!(expr, Expr::Primary(_))
+}
+
+// The rule for the principal formula at position i and the premises it leaves
+fn apply(seq: &Sequent, side_left: bool, i: usize) -> (String, Vec<Sequent>) {
+    use BinOperator::*;
+    let f = if side_left { &seq.left[i] } else { &seq.right[i] };
+    let side = if side_left { "L" } else { "R" };
+    let one = |l: Vec<Expr>, r: Vec<Expr>| vec![seq.with(side_left, i, l, r)];
+    let premises = match (f, side_left) {
+        (Expr::Not(a), true) => one(vec![], vec![*a.clone()]),
+        (Expr::Not(a), false) => one(vec![*a.clone()], vec![]),
+        (Expr::Binary(a, And, b), true) => one(vec![*a.clone(), *b.clone()], vec![]),
+        (Expr::Binary(a, And, b), false) => vec![seq.with(false, i, vec![], vec![*a.clone()]), seq.with(false, i, vec![], vec![*b.clone()])],
+        (Expr::Binary(a, Or, b), true) => vec![seq.with(true, i, vec![*a.clone()], vec![]), seq.with(true, i, vec![*b.clone()], vec![])],
+        (Expr::Binary(a, Or, b), false) => one(vec![], vec![*a.clone(), *b.clone()]),
+        (Expr::Binary(a, Arrow, b), true) => vec![seq.with(true, i, vec![], vec![*a.clone()]), seq.with(true, i, vec![*b.clone()], vec![])],
+        (Expr::Binary(a, Arrow, b), false) => one(vec![*a.clone()], vec![*b.clone()]),
+        (Expr::Binary(a, TwinArrow, b), true) => vec![
+            seq.with(true, i, vec![*a.clone(), *b.clone()], vec![]),
+            seq.with(true, i, vec![], vec![*a.clone(), *b.clone()]),
+        ],
+        (Expr::Binary(a, TwinArrow, b), false) => vec![
+            seq.with(false, i, vec![*a.clone()], vec![*b.clone()]),
+            seq.with(false, i, vec![*b.clone()], vec![*a.clone()]),
+        ],
+        // A quantifier over an atom is the conjunction or disjunction of its two instances
+        (Expr::Quantified(q, n, e), _) => {
+            let (low, high) = instances(e, *n);
+            match (q, side_left) {
+                (Quantifier::Forall, true) => one(vec![low, high], vec![]),
+                (Quantifier::Exists, false) => one(vec![], vec![low, high]),
+                (Quantifier::Forall, false) => vec![seq.with(false, i, vec![], vec![low]), seq.with(false, i, vec![], vec![high])],
+                (Quantifier::Exists, true) => vec![seq.with(true, i, vec![low], vec![]), seq.with(true, i, vec![high], vec![])],
+            }
+        },
+        // Only truth on the left and falsity on the right remain, they can be weakened away
+        _ => one(vec![], vec![]),
+    };
+    let name = match f {
+        Expr::Not(_) => "~".to_string(),
+        Expr::Binary(_, op, _) => op.to_string(),
+        Expr::Quantified(q, ..) => q.to_string(),
+        Expr::Constant(b) => (*b as usize).to_string(),
+        _ => String::new(),
+    };
+    (format!("{}{}", name, side), premises)
+}
+
+// Every rule is invertible, so the first compound formula can be decomposed without backtracking
+pub fn search(seq: Sequent) -> Proof {
+    if seq.is_axiom() {
+        return Proof { sequent: seq, rule: Some("Ax".to_string()), premises: Vec::new() };
+    }
+    let principal = seq.left
+        .iter()
+        .position(|f| !is_atomic(f))
+        .map(|i| (true, i))
+        .or_else(|| seq.right.iter().position(|f| !is_atomic(f)).map(|i| (false, i)));
+    match principal {
+        Some((side_left, i)) => {
+            let (rule, premises) = apply(&seq, side_left, i);
+            let premises = premises.into_iter().map(search).collect();
+            Proof { sequent: seq, rule: Some(rule), premises }
+        },
+        None => Proof { sequent: seq, rule: None, premises: Vec::new() },
+    }
+}
+
+impl Proof {
+    pub fn failing_leaf(&self) -> Option<&Sequent> {
+        match self.rule {
+            None => Some(&self.sequent),
+            Some(_) => self.premises.iter().find_map(|p| p.failing_leaf()),
+        }
+    }
+
+    // Lines of the rendered tree, all of the same width, with the premises above a bar and the rule name
+    fn render(&self, interned: &Interner) -> Vec<String> {
+        let conclusion = self.sequent.to_string(interned);
+        let Some(rule) = &self.rule else {
+            return vec![conclusion];
+        };
+        let blocks = self.premises.iter().map(|p| p.render(interned)).collect::<Vec<_>>();
+        let height = blocks.iter().map(|b| b.len()).max().unwrap_or(0);
+        let mut above = vec![String::new(); height];
+        for (k, block) in blocks.iter().enumerate() {
+            let width = block[0].chars().count();
+            let padding = height - block.len();
+            for (row, line) in above.iter_mut().enumerate() {
+                if k > 0 {
+                    line.push_str("   ");
+                }
+                if row < padding {
+                    line.push_str(&" ".repeat(width));
+                } else {
+                    line.push_str(&block[row - padding]);
+                }
+            }
+        }
+
+        let above_width = above.first().map_or(0, |l| l.chars().count());
+        let bar = above_width.max(conclusion.chars().count());
+        let width = bar + 1 + rule.len();
+        let center = |s: &str| {
+            let offset = (bar - s.chars().count()) / 2;
+            format!("{:<width$}", format!("{}{}", " ".repeat(offset), s), width = width)
+        };
+        let mut lines = above.iter().map(|l| center(l)).collect::<Vec<_>>();
+        lines.push(format!("{} {}", "-".repeat(bar), rule));
+        lines.push(center(&conclusion));
+        lines
+    }
+
+    pub fn print(&self, interned: &Interner) {
+        for line in self.render(interned) {
+            println!("{}", line.trim_end());
+        }
+    }
+
+    fn latex_lines(&self, interned: &Interner, lines: &mut Vec<String>) {
+        let conclusion = format!("${}$", sequent_to_latex(&self.sequent, interned));
+        let Some(rule) = &self.rule else {
+            lines.push(format!("\\AxiomC{{{}}}", conclusion));
+            return;
+        };
+        if self.premises.is_empty() {
+            lines.push("\\AxiomC{}".to_string());
+        }
+        for p in &self.premises {
+            p.latex_lines(interned, lines);
+        }
+        let inference = match self.premises.len() {
+            2 => "BinaryInfC",
+            _ => "UnaryInfC",
+        };
+        lines.push(format!("\\RightLabel{{\\scriptsize ${}$}}", rule_to_latex(rule)));
+        lines.push(format!("\\{}{{{}}}", inference, conclusion));
+    }
+
+    pub fn print_latex(&self, interned: &Interner) {
+        let mut lines = vec!["\\begin{prooftree}".to_string()];
+        self.latex_lines(interned, &mut lines);
+        lines.push("\\end{prooftree}".to_string());
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+}
+
+fn op_to_latex(op: &BinOperator) -> &'static str {
+    match op {
+        BinOperator::And => "\\land",
+        BinOperator::Or => "\\lor",
+        BinOperator::Arrow => "\\to",
+        BinOperator::TwinArrow => "\\leftrightarrow",
+    }
+}
+
+fn quantifier_to_latex(q: &Quantifier) -> &'static str {
+    match q {
+        Quantifier::Forall => "\\forall",
+        Quantifier::Exists => "\\exists",
+    }
+}
+
+// Same parentheses as the printed form, with atom names kept apart from the commands around them
+fn expr_to_latex(expr: &Expr, interned: &Interner) -> String {
+    match expr {
+        Expr::Binary(l, op, r) => format!("{} {} {}", operand_to_latex(l, interned, op, false), op_to_latex(op), operand_to_latex(r, interned, op, true)),
+        Expr::Not(e) => match **e {
+            Expr::Binary(..) | Expr::Quantified(..) => format!("\\lnot ({})", expr_to_latex(e, interned)),
+            _ => format!("\\lnot {}", expr_to_latex(e, interned)),
+        },
+        Expr::Group(e) => format!("({})", expr_to_latex(e, interned)),
+        Expr::Quantified(q, n, e) => format!("{} \\mathit{{{}}}. {}", quantifier_to_latex(q), interned[*n], expr_to_latex(e, interned)),
+        Expr::Primary(n) => format!("\\mathit{{{}}}", interned[*n]),
+        Expr::Constant(true) => "\\top".to_string(),
+        Expr::Constant(false) => "\\bot".to_string(),
+        // Statements never end up in a sequent
+        _ => utils::expr_to_string(expr, interned),
+    }
+}
+
+fn operand_to_latex(expr: &Expr, interned: &Interner, parent: &BinOperator, is_right: bool) -> String {
+    match expr {
+        Expr::Binary(_, op, _) if op.precedence() < parent.precedence() || (is_right && op.precedence() == parent.precedence()) =>
+            format!("({})", expr_to_latex(expr, interned)),
+        Expr::Quantified(..) => format!("({})", expr_to_latex(expr, interned)),
+        _ => expr_to_latex(expr, interned),
+    }
+}
+
+fn sequent_to_latex(seq: &Sequent, interned: &Interner) -> String {
+    let side = |fs: &[Expr]| fs.iter().map(|f| expr_to_latex(f, interned)).collect::<Vec<_>>().join(", ");
+    format!("{} \\vdash {}", side(&seq.left), side(&seq.right)).trim().to_string()
+}
+
+// Rule names are made by `apply` and `search`, the connective followed by the side
+fn rule_to_latex(rule: &str) -> String {
+    if rule == "Ax" {
+        return "\\mathrm{Ax}".to_string();
+    }
+    let (name, side) = rule.split_at(rule.len() - 1);
+    let name = match name {
+        "~" => "\\lnot",
+        "&" => "\\land",
+        "|" => "\\lor",
+        "->" => "\\to",
+        "<->" => "\\leftrightarrow",
+        "forall" => "\\forall",
+        "exists" => "\\exists",
+        "1" => "\\top",
+        "0" => "\\bot",
+        _ => name,
+    };
+    format!("{} \\mathrm{{{}}}", name, side)
+}
+
+pub fn countermodel(leaf: &Sequent, interned: &Interner) -> String {
+    let mut values = leaf.left
+        .iter()
+        .map(|f| (f, 1))
+        .chain(leaf.right.iter().map(|f| (f, 0)))
+        .map(|(f, v)| format!("{} = {}", utils::expr_to_string(f, interned), v))
+        .collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+    values.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    fn prove(input: &str, interned: &mut Interner) -> Proof {
+        let Ok(Expr::Argument(premises, goal)) = parser::parse_line(input, interned, &History::new()) else {
+            panic!("expected an argument");
+        };
+        search(Sequent::new(&premises, &goal))
+    }
+
+    fn latex(proof: &Proof, interned: &Interner) -> Vec<String> {
+        let mut lines = Vec::new();
+        proof.latex_lines(interned, &mut lines);
+        lines
+    }
+
+    #[test]
+    fn proves_valid_sequents() {
+        let mut interned = Interner::new();
+        assert!(prove("p -> q, p |- q", &mut interned).failing_leaf().is_none());
+        assert!(prove("|- p | ~p", &mut interned).failing_leaf().is_none());
+        assert!(prove("|- (forall p. p) -> q", &mut interned).failing_leaf().is_none());
+    }
+
+    #[test]
+    fn reports_a_countermodel() {
+        let mut interned = Interner::new();
+        let proof = prove("p -> q |- q", &mut interned);
+        let leaf = proof.failing_leaf().expect("the sequent is not valid");
+        assert_eq!(countermodel(leaf, &interned), "p = 0, q = 0");
+    }
+
+    #[test]
+    fn latex_keeps_atom_names_intact() {
+        let mut interned = Interner::new();
+        let proof = prove("Ax, forallx |- Ax & 1", &mut interned);
+        let lines = latex(&proof, &interned);
+        assert!(lines.contains(&"\\BinaryInfC{$\\mathit{Ax}, \\mathit{forallx} \\vdash \\mathit{Ax} \\land \\top$}".to_string()));
+        assert!(lines.contains(&"\\RightLabel{\\scriptsize $\\land \\mathrm{R}$}".to_string()));
+        assert!(lines.contains(&"\\RightLabel{\\scriptsize $\\mathrm{Ax}$}".to_string()));
+    }
+
+    #[test]
+    fn latex_of_quantifiers_and_negations() {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula("~(forall p. p -> q) | ~r", &mut interned, &History::new()).unwrap();
+        assert_eq!(
+            expr_to_latex(&runtime::strip_groups(&expr), &interned),
+            "\\lnot (\\forall \\mathit{p}. \\mathit{p} \\to \\mathit{q}) \\lor \\lnot \\mathit{r}"
+        );
+    }
+}
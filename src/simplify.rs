@@ -0,0 +1,155 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashSet}, sync::OnceLock};
+use crate::{parser::{BinOperator, Expr}, runtime, stdlib};
+
+const MAX_VISITED: usize = 5000;
+const COST_SLACK: usize = 4;
+
+static LAWS: OnceLock<Vec<(Expr, Expr)>> = OnceLock::new();
+
+// The standard laws, parsed once. Laws without arrows are applied both ways and laws with arrows on one side
+// only towards the other side, so the simplified form never reintroduces arrows
+fn laws() -> &'static [(Expr, Expr)] {
+    LAWS.get_or_init(|| {
+        let mut laws = Vec::new();
+        for (_, lhs, rhs) in stdlib::laws() {
+            let (lhs, rhs) = (runtime::strip_groups(lhs), runtime::strip_groups(rhs));
+            match (has_arrow(&lhs), has_arrow(&rhs)) {
+                (false, false) => {
+                    laws.push((lhs.clone(), rhs.clone()));
+                    laws.push((rhs, lhs));
+                },
+                (true, false) => laws.push((lhs, rhs)),
+                (false, true) => laws.push((rhs, lhs)),
+                (true, true) => {},
+            }
+        }
+        laws
+    })
+}
+
+fn has_arrow(expr: &Expr) -> bool {
+    match expr {
+        Expr::Binary(l, op, r) => matches!(op, BinOperator::Arrow | BinOperator::TwinArrow) || has_arrow(l) || has_arrow(r),
+        Expr::Not(e) | Expr::Group(e) | Expr::Quantified(_, _, e) => has_arrow(e),
+        _ => false,
+    }
+}
+
+// Number of atoms, constants and operators, with the depth of the tree as tie-breaker
+pub fn cost(expr: &Expr) -> (usize, usize) {
+    match expr {
+        Expr::Binary(l, _, r) => {
+            let (left_size, left_depth) = cost(l);
+            let (right_size, right_depth) = cost(r);
+            (left_size + right_size + 1, left_depth.max(right_depth) + 1)
+        },
+        Expr::Not(e) => {
+            let (size, depth) = cost(e);
+            (size + 1, depth + 1)
+        },
+        Expr::Group(e) => cost(e),
+        Expr::Quantified(_, _, e) => {
+            let (size, depth) = cost(e);
+            (size + 1, depth + 1)
+        },
+        _ => (1, 1),
+    }
+}
+
+pub fn simplify(expr: &Expr) -> Expr {
+    let laws = laws();
+    let start = runtime::strip_groups(expr);
+    let limit = cost(&start).0 + COST_SLACK;
+
+    let mut best = start.clone();
+    let mut visited: HashSet<Expr> = HashSet::new();
+    let mut queue = BinaryHeap::new();
+    visited.insert(start.clone());
+    queue.push(Reverse((cost(&start), start)));
+
+    while let Some(Reverse((c, e))) = queue.pop() {
+        if c < cost(&best) {
+            best = e.clone();
+        }
+        if visited.len() >= MAX_VISITED {
+            continue;
+        }
+        for (lhs, rhs) in laws {
+            for next in runtime::rewrite_subterms(&e, lhs, rhs) {
+                let next = runtime::strip_groups(&next);
+                let c = cost(&next);
+                if c.0 <= limit && visited.insert(next.clone()) {
+                    queue.push(Reverse((c, next)));
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, interner::Interner, parser};
+
+    fn simplified(input: &str) -> (Expr, Expr) {
+        let mut interned = Interner::new();
+        let history = History::new();
+        let expr = parser::parse_formula(input, &mut interned, &history).unwrap();
+        (expr.clone(), simplify(&expr))
+    }
+
+    fn parsed(input: &str, interned: &mut Interner) -> Expr {
+        runtime::strip_groups(&parser::parse_formula(input, interned, &History::new()).unwrap())
+    }
+
+    #[test]
+    fn quantified_bodies_count_towards_the_cost() {
+        let mut interned = Interner::new();
+        assert_eq!(cost(&parsed("forall p. q & 1", &mut interned)), (4, 3));
+        assert!(cost(&parsed("forall p. q", &mut interned)) < cost(&parsed("forall p. q & 1", &mut interned)));
+    }
+
+    #[test]
+    fn simplifies_inside_quantifiers() {
+        let (start, best) = simplified("forall p. q & 1");
+        assert!(cost(&best) < cost(&start));
+        let Expr::Quantified(_, _, body) = best else { panic!("expected a quantifier") };
+        assert!(matches!(*body, Expr::Primary(_)));
+    }
+
+    #[test]
+    fn simplifies_propositional_laws() {
+        let (_, best) = simplified("(a & b) | (a & ~b)");
+        assert!(matches!(best, Expr::Primary(_)));
+        let (_, best) = simplified("a | ~a");
+        assert!(matches!(best, Expr::Constant(true)));
+    }
+
+    #[test]
+    fn cost_counts_size_then_depth() {
+        let mut interned = Interner::new();
+        assert_eq!(cost(&parsed("a", &mut interned)), (1, 1));
+        assert_eq!(cost(&parsed("~a & (b | c)", &mut interned)), (6, 3));
+        assert!(cost(&parsed("(a & b) & (c & d)", &mut interned)) < cost(&parsed("a & (b & (c & d))", &mut interned)));
+    }
+
+    #[test]
+    fn arrows_are_eliminated_and_meaning_is_kept() {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula("(a -> b) & (b -> a) & a", &mut interned, &History::new()).unwrap();
+        let best = simplify(&expr);
+        assert!(runtime::is_equivalent(&expr, &best, &interned).unwrap());
+        assert!(cost(&best) < cost(&runtime::strip_groups(&expr)));
+        let printed = crate::utils::expr_to_string(&best, &interned);
+        assert!(!printed.contains("->"), "{}", printed);
+    }
+
+    #[test]
+    fn laws_come_from_the_standard_library_without_introducing_arrows() {
+        assert!(!laws().is_empty());
+        assert!(laws().iter().all(|(_, rhs)| !has_arrow(rhs)));
+        assert_eq!(laws().len(), stdlib::laws().filter(|(_, lhs, rhs)| !has_arrow(lhs) || !has_arrow(rhs)).count() * 2
+            - stdlib::laws().filter(|(_, lhs, rhs)| has_arrow(lhs) != has_arrow(rhs)).count());
+    }
+}
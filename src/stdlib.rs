@@ -0,0 +1,119 @@
+use std::{collections::HashMap, sync::OnceLock};
+use crate::{parser::{self, Expr, Rule}, interner::Interner, runtime};
+
+pub const NAMESPACE: &str = "std::";
+
+const LAWS: &[&str] = &[
+    "std::commAnd := p & q = q & p",
+    "std::commOr := p | q = q | p",
+    "std::assocAnd := (p & q) & r = p & (q & r)",
+    "std::assocOr := (p | q) | r = p | (q | r)",
+    "std::distAnd := p & (q | r) = (p & q) | (p & r)",
+    "std::distOr := p | (q & r) = (p | q) & (p | r)",
+    "std::demorganAnd := ~(p & q) = ~p | ~q",
+    "std::demorganOr := ~(p | q) = ~p & ~q",
+    "std::doubleNeg := ~~p = p",
+    "std::implElim := p -> q = ~p | q",
+    "std::contraposition := p -> q = ~q -> ~p",
+    "std::exportation := (p & q) -> r = p -> (q -> r)",
+    "std::bicondElim := p <-> q = (p -> q) & (q -> p)",
+    "std::absorbAnd := p & (p | q) = p",
+    "std::absorbOr := p | (p & q) = p",
+    "std::identAnd := p & 1 = p",
+    "std::identOr := p | 0 = p",
+    "std::domAnd := p & 0 = 0",
+    "std::domOr := p | 1 = 1",
+    "std::complementAnd := p & ~p = 0",
+    "std::complementOr := p | ~p = 1",
+    "std::negTrue := ~1 = 0",
+    "std::negFalse := ~0 = 1",
+    "std::idempAnd := p & p = p",
+    "std::idempOr := p | p = p",
+    "std::bicondCases := p <-> q = (p & q) | (~p & ~q)",
+    "std::bicondRefl := p <-> p = 1",
+];
+
+// A law by name, with both sides or the parse error
+type Law = (&'static str, Result<(Expr, Expr), String>);
+
+struct Library {
+    patterns: Interner,
+    laws: Vec<Law>,
+}
+
+static LIBRARY: OnceLock<Library> = OnceLock::new();
+
+// The laws are parsed once with their own interner, so the pattern variables p, q, r do not become atoms of the session
+fn library() -> &'static Library {
+    LIBRARY.get_or_init(|| {
+        let mut patterns = Interner::new();
+        let laws = LAWS.iter()
+            .map(|law| (law.split_once(":=").map_or("", |(name, _)| name.trim()), parser::parse_rule(law, &mut patterns)))
+            .collect();
+        Library { patterns, laws }
+    })
+}
+
+// The names of the pattern variables in the laws
+pub fn patterns() -> &'static Interner {
+    &library().patterns
+}
+
+// Both sides of every law that parsed, in the order of the library
+pub fn laws() -> impl Iterator<Item = (&'static str, &'static Expr, &'static Expr)> {
+    library().laws.iter().filter_map(|(name, law)| law.as_ref().ok().map(|(lhs, rhs)| (*name, lhs, rhs)))
+}
+
+// Binds every law whose sides are equivalent by truth table, and returns an error message for any other law
+pub fn load(rule_bindings: &mut HashMap<String, Rule>) -> Vec<String> {
+    let Library { patterns, laws } = library();
+    let mut errors = Vec::new();
+    for (name, law) in laws {
+        match law {
+            Ok((lhs, rhs)) if runtime::is_equivalent(lhs, rhs, patterns) == Ok(true) => {
+                rule_bindings.insert(name.to_string(), Rule::Equivalence(lhs.clone(), rhs.clone()));
+            },
+            Ok(_) => errors.push(format!("Standard law {} is not sound and was not loaded", name)),
+            Err(msg) => errors.push(format!("Standard law {} could not be parsed: {}", name, msg)),
+        }
+    }
+    errors
+}
+
+pub fn unload(rule_bindings: &mut HashMap<String, Rule>) {
+    rule_bindings.retain(|name, _| !name.starts_with(NAMESPACE));
+}
+
+// Names of the loaded laws in the order of the library
+pub fn names(rule_bindings: &HashMap<String, Rule>) -> Vec<String> {
+    LAWS.iter()
+        .filter_map(|law| law.split_once(":=").map(|(name, _)| name.trim().to_string()))
+        .filter(|name| rule_bindings.contains_key(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_law_is_sound_and_loaded() {
+        let mut rule_bindings = HashMap::new();
+        assert!(load(&mut rule_bindings).is_empty());
+        assert_eq!(names(&rule_bindings).len(), LAWS.len());
+        assert!(names(&rule_bindings).iter().all(|name| name.starts_with(NAMESPACE)));
+    }
+
+    #[test]
+    fn unload_keeps_user_rules() {
+        let mut interned = Interner::new();
+        let mut rule_bindings = HashMap::new();
+        let (lhs, rhs) = parser::parse_rule("comm := p & q = q & p", &mut interned).unwrap();
+        rule_bindings.insert("comm".to_string(), Rule::Equivalence(lhs, rhs));
+        load(&mut rule_bindings);
+        assert_eq!(interned.names(), ["comm", "p", "q"]);
+        unload(&mut rule_bindings);
+        assert!(names(&rule_bindings).is_empty());
+        assert_eq!(rule_bindings.keys().collect::<Vec<_>>(), vec!["comm"]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{parser::Expr, interner::Interner, minimize::{self, Implicant}};
+
+#[derive(Debug)]
+pub struct Spec {
+    pub vars: Vec<String>,
+    pub on: Vec<usize>,
+    pub dc: Vec<usize>,
+}
+
+fn is_column(word: &str) -> bool {
+    word.len() >= 2
+        && word.chars().all(|c| matches!(c, '0' | '1' | '-' | 'x'))
+        && word.chars().any(|c| matches!(c, '0' | '1'))
+}
+
+pub fn is_spec(input: &str) -> bool {
+    let compact = input.replace(' ', "");
+    compact.starts_with("summ(")
+        || compact.starts_with("prodM(")
+        || is_column(input.split_whitespace().next().unwrap_or(""))
+}
+
+// Parses "name(i, j, ..)" at the start of `input` and returns the numbers and the remaining input
+fn index_list<'a>(input: &'a str, name: &str) -> Result<(Vec<usize>, &'a str), String> {
+    let rest = input.trim_start();
+    let Some(rest) = rest.strip_prefix(name).map(|r| r.trim_start()).and_then(|r| r.strip_prefix('(')) else {
+        return Err(format!("Expected '{}(' in function specification", name));
+    };
+    let Some((list, rest)) = rest.split_once(')') else {
+        return Err("Missing closing parenthesis in function specification".to_string());
+    };
+    let indices = list
+        .split(',')
+        .map(|i| i.trim())
+        .filter(|i| !i.is_empty())
+        .map(|i| i.parse::<usize>().map_err(|_| format!("Expected a row number, found '{}'", i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    match indices.iter().find(|i| **i >= 1 << minimize::MAX_VARS) {
+        Some(row) => Err(format!("Row {} does not exist for at most {} variables", row, minimize::MAX_VARS)),
+        None => Ok((indices, rest)),
+    }
+}
+
+// A row cannot be both a term and a don't-care
+fn disjoint(terms: &[usize], dc: &[usize], kind: &str) -> Result<(), String> {
+    match terms.iter().find(|i| dc.contains(i)) {
+        Some(row) => Err(format!("Row {} is listed both as a {} and as a don't-care", row, kind)),
+        None => Ok(()),
+    }
+}
+
+fn default_vars(rows: usize) -> Vec<String> {
+    let mut count = 1;
+    while (1 << count) < rows {
+        count += 1;
+    }
+    (0..count).map(|i| ((b'a' + i as u8) as char).to_string()).collect()
+}
+
+// The first variable is the most significant bit of a row number, as in "sum m(1, 3) over a, b"
+pub fn parse_spec(input: &str) -> Result<Spec, String> {
+    let (body, vars) = match input.split_once(" over ") {
+        Some((body, vars)) => (body.trim(), Some(vars.split(',').map(|v| v.trim().to_string()).collect::<Vec<_>>())),
+        None => (input.trim(), None),
+    };
+    if let Some(vars) = &vars {
+        if vars.iter().any(|v| v.is_empty() || !v.chars().all(|c| c.is_ascii_alphabetic())) {
+            return Err("Expected a comma separated list of atoms after 'over'".to_string());
+        }
+        if let Some((_, v)) = vars.iter().enumerate().find(|(i, v)| vars[..*i].contains(v)) {
+            return Err(format!("Variable {} is listed more than once after 'over'", v));
+        }
+        minimize::check_vars(vars.len())?;
+    }
+
+    let (on, dc, rows) = if let Some(rest) = body.strip_prefix("sum") {
+        let (on, rest) = index_list(rest, "m")?;
+        let (dc, _) = if rest.trim().is_empty() { (Vec::new(), rest) } else { index_list(rest, "d")? };
+        disjoint(&on, &dc, "minterm")?;
+        let rows = on.iter().chain(dc.iter()).max().map_or(2, |m| m + 1);
+        (on, dc, rows)
+    } else if let Some(rest) = body.strip_prefix("prod") {
+        let (off, rest) = index_list(rest, "M")?;
+        let (dc, _) = if rest.trim().is_empty() { (Vec::new(), rest) } else { index_list(rest, "d")? };
+        disjoint(&off, &dc, "maxterm")?;
+        let rows = match &vars {
+            Some(vars) => 1 << vars.len(),
+            None => off.iter().chain(dc.iter()).max().map_or(2, |m| m + 1).next_power_of_two(),
+        };
+        let on = (0..rows).filter(|i| !off.contains(i) && !dc.contains(i)).collect::<Vec<_>>();
+        (on, dc, rows)
+    } else if is_column(body) {
+        if !body.len().is_power_of_two() {
+            return Err(format!("A result column needs a power of two rows, found {}", body.len()));
+        }
+        minimize::check_vars(body.len().trailing_zeros() as usize)?;
+        let column = body.chars().collect::<Vec<_>>();
+        let on = (0..column.len()).filter(|i| column[*i] == '1').collect::<Vec<_>>();
+        let dc = (0..column.len()).filter(|i| matches!(column[*i], '-' | 'x')).collect::<Vec<_>>();
+        (on, dc, column.len())
+    } else {
+        return Err("Expected 'sum m(..)', 'prod M(..)' or a result column such as 0110".to_string());
+    };
+
+    let vars = vars.unwrap_or_else(|| default_vars(rows));
+    if rows > 1 << vars.len() {
+        return Err(format!("Row {} does not exist for {} variables", rows - 1, vars.len()));
+    }
+    if body.chars().all(|c| matches!(c, '0' | '1' | '-' | 'x')) && rows != 1 << vars.len() {
+        return Err(format!("A result column for {} variables needs {} rows", vars.len(), 1 << vars.len()));
+    }
+    Ok(Spec { vars, on, dc })
+}
+
+pub struct Synthesis {
+    pub dnf: Expr,
+    pub cnf: Expr,
+    pub minimal: Expr,
+}
+
+pub fn synthesize(spec: &Spec, interned: &mut Interner) -> Synthesis {
+    // Fresh atoms are interned last to first, so the truth table shows the first variable as leftmost column
+    // Bit j of a row number is atom j, so the first variable is the highest bit
+    let bits = spec.vars.len();
+    let atoms = spec.vars
+        .iter()
+        .rev()
+        .map(|v| Expr::Primary(interned.intern(v)))
+        .collect::<Vec<_>>();
+    let (on, dc) = (&spec.on, &spec.dc);
+    let off = (0..1 << bits)
+        .filter(|i| !on.contains(i) && !dc.contains(i))
+        .collect::<Vec<_>>();
+    let to_implicants = |rows: &[usize]| rows
+        .iter()
+        .map(|m| Implicant { value: *m, mask: 0 })
+        .collect::<Vec<_>>();
+    let cover = minimize::minimal_cover(bits, on, dc);
+
+    Synthesis {
+        dnf: minimize::to_sop(&to_implicants(on), &atoms),
+        cnf: minimize::to_pos(&to_implicants(&off), &atoms),
+        minimal: minimize::to_sop(&cover, &atoms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn synth_str(input: &str) -> Vec<String> {
+        let mut interned = Interner::new();
+        let spec = parse_spec(input).unwrap();
+        let res = synthesize(&spec, &mut interned);
+        [res.dnf, res.cnf, res.minimal].iter().map(|e| utils::expr_to_string(e, &interned)).collect()
+    }
+
+    #[test]
+    fn sum_prod_and_column_agree() {
+        let xor = vec!["(~a & b) | (a & ~b)", "(a | b) & (~a | ~b)", "(~a & b) | (a & ~b)"];
+        assert_eq!(synth_str("sum m(1, 2) over a, b"), xor);
+        assert_eq!(synth_str("prod M(0, 3) over a, b"), xor);
+        assert_eq!(synth_str("0110"), xor);
+    }
+
+    #[test]
+    fn dont_cares_are_left_to_the_minimal_form() {
+        assert_eq!(synth_str("sum m(1, 2) d(3) over a, b")[2], "b | a");
+        assert_eq!(synth_str("01-1")[2], "b");
+    }
+
+    #[test]
+    fn default_variables_fit_the_highest_row() {
+        let spec = parse_spec("sum m(4)").unwrap();
+        assert_eq!(spec.vars, vec!["a", "b", "c"]);
+        assert!(parse_spec("sum m(4) over a, b").is_err());
+        assert!(parse_spec("011").is_err());
+    }
+
+    #[test]
+    fn rejects_repeated_variables() {
+        assert!(parse_spec("sum m(1) over a, a").is_err());
+        assert!(parse_spec("0110 over p, p").is_err());
+    }
+
+    #[test]
+    fn rejects_rows_that_are_also_dont_cares() {
+        assert!(parse_spec("sum m(1, 3) d(3)").is_err());
+        assert!(parse_spec("prod M(1) d(1) over a, b").is_err());
+        assert!(parse_spec("sum m(1) d(3)").is_ok());
+    }
+
+    #[test]
+    fn rejects_more_variables_than_minimization_supports() {
+        let over = (1..=70).map(|i| "x".repeat(i)).collect::<Vec<_>>().join(", ");
+        assert!(parse_spec(&format!("sum m(1) over {}", over)).is_err());
+        assert!(parse_spec("prod M(0) over a, b, c, d, e, f, g").is_err());
+        assert!(parse_spec("sum m(100000000)").is_err());
+        assert!(parse_spec(&format!("sum m({})", usize::MAX)).is_err());
+        assert!(parse_spec(&"01".repeat(64)).is_err());
+        assert_eq!(parse_spec("sum m(63)").unwrap().vars.len(), minimize::MAX_VARS);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{parser::{Expr, BinOperator, Quantifier}, interner::Interner, runtime::{self, instances, not}, utils};
+
+// The formulas added to a branch by one expansion, followed by the branches it splits into
+pub struct Node {
+    formulas: Vec<Expr>,
+    children: Vec<Node>,
+    closed: Option<Expr>,
+}
+
+enum Expansion {
+    Alpha(Vec<Expr>),
+    Beta(Vec<Expr>, Vec<Expr>),
+    Literal,
+}
+
+fn complement(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Not(e) => *e.clone(),
+        e => not(e),
+    }
+}
+
+// Quantifiers over an atom are expanded into the instances for both truth values
+fn expansion(expr: &Expr) -> Expansion {
+    use BinOperator::*;
+    match expr {
+        Expr::Binary(l, And, r) => Expansion::Alpha(vec![*l.clone(), *r.clone()]),
+        Expr::Binary(l, Or, r) => Expansion::Beta(vec![*l.clone()], vec![*r.clone()]),
+        Expr::Binary(l, Arrow, r) => Expansion::Beta(vec![not(l)], vec![*r.clone()]),
+        Expr::Binary(l, TwinArrow, r) => Expansion::Beta(vec![*l.clone(), *r.clone()], vec![not(l), not(r)]),
+        Expr::Quantified(Quantifier::Forall, n, e) => {
+            let (low, high) = instances(e, *n);
+            Expansion::Alpha(vec![low, high])
+        },
+        Expr::Quantified(Quantifier::Exists, n, e) => {
+            let (low, high) = instances(e, *n);
+            Expansion::Beta(vec![low], vec![high])
+        },
+        Expr::Not(inner) => match &**inner {
+            Expr::Not(e) => Expansion::Alpha(vec![*e.clone()]),
+            Expr::Binary(l, And, r) => Expansion::Beta(vec![not(l)], vec![not(r)]),
+            Expr::Binary(l, Or, r) => Expansion::Alpha(vec![not(l), not(r)]),
+            Expr::Binary(l, Arrow, r) => Expansion::Alpha(vec![*l.clone(), not(r)]),
+            Expr::Binary(l, TwinArrow, r) => Expansion::Beta(vec![*l.clone(), not(r)], vec![not(l), *r.clone()]),
+            Expr::Quantified(Quantifier::Forall, n, e) => {
+                let (low, high) = instances(e, *n);
+                Expansion::Beta(vec![not(&low)], vec![not(&high)])
+            },
+            Expr::Quantified(Quantifier::Exists, n, e) => {
+                let (low, high) = instances(e, *n);
+                Expansion::Alpha(vec![not(&low), not(&high)])
+            },
+            _ => Expansion::Literal,
+        },
+        _ => Expansion::Literal,
+    }
+}
+
+// A branch closes on falsity or on a formula together with its complement
+fn closes(expr: &Expr, branch: &[Expr]) -> Option<Expr> {
+    match expr {
+        Expr::Constant(false) => Some(expr.clone()),
+        Expr::Not(e) if **e == Expr::Constant(true) => Some(expr.clone()),
+        e if branch.contains(&complement(e)) => Some(e.clone()),
+        _ => None,
+    }
+}
+
+// Adds formulas to the branch and returns the formula that closes it, if any
+fn add(node: &mut Node, formulas: Vec<Expr>, branch: &mut Vec<Expr>, pending: &mut Vec<Expr>) -> Option<Expr> {
+    for f in formulas {
+        let f = runtime::strip_groups(&f);
+        node.formulas.push(f.clone());
+        if let Some(c) = closes(&f, branch) {
+            return Some(c);
+        }
+        if !matches!(expansion(&f), Expansion::Literal) {
+            pending.push(f.clone());
+        }
+        branch.push(f);
+    }
+    None
+}
+
+// Alpha formulas are expanded before the branch is split on a beta formula
+fn expand(mut node: Node, formulas: Vec<Expr>, mut branch: Vec<Expr>, mut pending: Vec<Expr>) -> Node {
+    let mut formulas = formulas;
+    loop {
+        if let Some(c) = add(&mut node, formulas, &mut branch, &mut pending) {
+            node.closed = Some(c);
+            return node;
+        }
+        let next = pending
+            .iter()
+            .position(|f| matches!(expansion(f), Expansion::Alpha(_)))
+            .or(if pending.is_empty() { None } else { Some(0) });
+        let Some(i) = next else {
+            return node;
+        };
+        match expansion(&pending.remove(i)) {
+            Expansion::Alpha(components) => formulas = components,
+            Expansion::Beta(left, right) => {
+                for components in [left, right] {
+                    let child = Node { formulas: Vec::new(), children: Vec::new(), closed: None };
+                    node.children.push(expand(child, components, branch.clone(), pending.clone()));
+                }
+                return node;
+            },
+            Expansion::Literal => formulas = Vec::new(),
+        }
+    }
+}
+
+pub fn build(expr: &Expr) -> Node {
+    let root = Node { formulas: Vec::new(), children: Vec::new(), closed: None };
+    expand(root, vec![not(&runtime::strip_groups(expr))], Vec::new(), Vec::new())
+}
+
+impl Node {
+    // The literals of the leftmost open branch
+    pub fn open_branch(&self) -> Option<Vec<Expr>> {
+        if self.closed.is_some() {
+            return None;
+        }
+        if self.children.is_empty() {
+            return Some(self.formulas.clone());
+        }
+        self.children.iter().find_map(|c| c.open_branch()).map(|rest| {
+            let mut branch = self.formulas.clone();
+            branch.extend(rest);
+            branch
+        })
+    }
+
+    pub fn print(&self, interned: &Interner) {
+        self.print_with("", "", interned);
+    }
+
+    fn print_with(&self, first: &str, prefix: &str, interned: &Interner) {
+        for (i, f) in self.formulas.iter().enumerate() {
+            let lead = if i == 0 { first } else { prefix };
+            println!("{}{}", lead, utils::expr_to_string(f, interned));
+        }
+        match &self.closed {
+            Some(c) => println!("{}x closed: {} and {}", prefix, utils::expr_to_string(c, interned), utils::expr_to_string(&complement(c), interned)),
+            None if self.children.is_empty() => println!("{}o open", prefix),
+            None => {},
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            if i + 1 == self.children.len() {
+                child.print_with(&format!("{}`-- ", prefix), &format!("{}    ", prefix), interned);
+            } else {
+                child.print_with(&format!("{}|-- ", prefix), &format!("{}|   ", prefix), interned);
+            }
+        }
+    }
+}
+
+// Atoms that do not occur as a literal on the open branch can take any value, they are set to false
+pub fn countermodel(branch: &[Expr], expr: &Expr, interned: &Interner) -> String {
+    runtime::free_atoms(expr)
+        .iter()
+        .map(|n| {
+            let value = branch.contains(&Expr::Primary(*n)) as usize;
+            format!("{} = {}", interned[*n], value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, parser};
+
+    fn countermodel_of(input: &str) -> Option<String> {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula(input, &mut interned, &History::new()).unwrap();
+        build(&expr).open_branch().map(|branch| countermodel(&branch, &expr, &interned))
+    }
+
+    #[test]
+    fn valid_formulas_close() {
+        for input in ["p | ~p", "(p -> q) & p -> q", "((p -> q) -> p) -> p", "(p <-> q) <-> (q <-> p)", "~(p & ~p)", "1", "forall p. p | ~p", "(forall p. p) -> q"] {
+            assert_eq!(countermodel_of(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn open_branches_give_a_countermodel() {
+        assert_eq!(countermodel_of("p -> q"), Some("p = 1, q = 0".to_string()));
+        assert_eq!(countermodel_of("p | q"), Some("p = 0, q = 0".to_string()));
+        assert_eq!(countermodel_of("0"), Some(String::new()));
+        assert_eq!(countermodel_of("exists p. p & q"), Some("q = 0".to_string()));
+    }
+
+    #[test]
+    fn closed_branches_record_the_contradiction() {
+        let mut interned = Interner::new();
+        let expr = parser::parse_formula("p -> p", &mut interned, &History::new()).unwrap();
+        let tree = build(&expr);
+        let p = Expr::Primary(interned.intern("p"));
+        let mut closed = Vec::new();
+        fn collect(node: &Node, out: &mut Vec<Expr>) {
+            out.extend(node.closed.clone());
+            node.children.iter().for_each(|c| collect(c, out));
+        }
+        collect(&tree, &mut closed);
+        assert!(closed.iter().all(|c| *c == p || *c == not(&p)), "{:?}", closed);
+        assert!(!closed.is_empty());
+    }
+}
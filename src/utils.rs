@@ -1,4 +1,5 @@
-use crate::parser::{Expr, Rule};
+use std::collections::HashMap;
+use crate::{parser::{Expr, Rule, BinOperator}, interner::Interner};
 
 pub fn usage(){
     println!("Usage:");
@@ -8,6 +9,10 @@ pub fn usage(){
     println!("   | Not oprator      |  '~'  | 'not'     |");
     println!("   | Cond oprator     |  '->' | 'implies' |");
     println!("   | Bi-Cond oprator  | '<->' | 'equiv'   |");
+    println!("   | True constant    |  '1'  | 'true'    |");
+    println!("   | False constant   |  '0'  | 'false'   |");
+    println!("   | For all          |  '∀'  | 'forall'  |");
+    println!("   | There exists     |  '∃'  | 'exists'  |");
     println!("   ----------------------------------------");
     println!("   -----------------------------------------------------------------------");
     println!("   | Rule       |  ':='  | identifier-name := lhs-pattern = rhs-pattern  |");
@@ -16,28 +21,108 @@ pub fn usage(){
     println!("   | Rule       |  '=>'  | inline pattern: A & B => p & q = q & p        |");
     println!("   | Derivation |        | bound pattern : A & B => commutative          |");
     println!("   -----------------------------------------------------------------------");
-    println!("   - help:   usage info");
-    println!("   - ans:    previous answer");
-    println!("   - toggle: toggle between (T/F) and (1/0) in truth tables");
-    println!("   - quit:   exit repl");
+    println!("   | Argument   |  '|-'  | premises and conclusion: p -> q, p |- q       |");
+    println!("   |            |        | keyword 'therefore': p -> q, p therefore q    |");
+    println!("   -----------------------------------------------------------------------");
+    println!("   - help:    usage info");
+    println!("   - ans:     previous answer, also 'ans[-n]' for the n-th last answer");
+    println!("   - $n:      n-th answer in the history");
+    println!("   - history [expr]: list all numbered answers, or add an expression to the history");
+    println!("   - atoms [a, b, ..]: list the atoms in truth table column order, or add atoms to the end");
+    println!("   - let <name> = <expr>: define a name that stands for the expression in later input");
+    println!("   - load <path>: run a .plg file of rules, definitions and expressions, '#' starts a comment");
+    println!("   - save <path>: write the session to a .plg file, 'restore <path>' replaces the session with it");
+    println!("   - std [name|on|off]: list or inspect the standard laws std::.., or (un)load them");
+    println!("   - toggle:  toggle between (T/F) and (1/0) in truth tables");
+    println!("   - logic [classical|k3|l3]: show or change the logic of truth tables, with T/U/F for three values");
+    println!("   - tableau <expr>: semantic tableau of the negation, with a countermodel if it does not close");
+    println!("   - resolve [premise, .. |-] <goal>: resolution refutation of the premises and the negated goal");
+    println!("   - sequent [latex] [premise, .. |-] <goal>: cut-free LK proof tree, in ASCII or LaTeX bussproofs");
+    println!("   - fitch <path>: check a natural deduction proof in Fitch notation");
+    println!("   - tautology <expr>: check if an expression is true for every assignment in the current logic");
+    println!("   - equivalent <expr>, <expr>: check if two expressions agree on every assignment in the current logic");
+    println!("   - derivation: start a derivation, one 'expr [RuleName]' per line, 'end' checks it");
+    println!("   - chain <expr>, <expr>: shortest chain of bound rule applications between two expressions");
+    println!("   - simplify <expr>: simplest equivalent form found with the built-in laws");
+    println!("   - minimize <expr> [dc i,j,..]: minimal sum-of-products and product-of-sums,");
+    println!("                 optionally with don't-care truth table rows i, j, ..");
+    println!("   - kmap <expr> [dc i,j,..]: Karnaugh map of 2 to 6 variables with the minimal groups");
+    println!("   - sum m(i,..) [d(j,..)] [over a,b,..]: function from minterms (and don't-cares)");
+    println!("   - prod M(i,..) [d(j,..)] [over a,b,..]: function from maxterms (and don't-cares)");
+    println!("   - 0110.. [over a,b,..]: function from a result column, '-' marks a don't-care");
+    println!("   - basis <basis> <expr>: rewrite into the basis {{~, &}}, {{~, |}}, {{~, ->}}, nand or nor");
+    println!("   - complete {{c, ..}}: check if a set of connectives is functionally complete");
+    println!("   - tnorm [godel|product|lukasiewicz]: show or change the t-norm of fuzzy evaluation");
+    println!("   - eval <expr> with p=0.5, ..: fuzzy truth value of an expression for values in [0, 1]");
+    println!("   - surface <expr> [steps n]: fuzzy truth values with every atom sampled at n + 1 points");
+    println!("   - count <expr>: count the assignments that satisfy an expression");
+    println!("   - prob <expr> with p=0.5, ..: probability that an expression holds for independent atoms");
+    println!("   - quit:    exit repl");
+}
+
+pub fn cli_usage(){
+    println!("Usage: plogic [options] [script.plg]");
+    println!("   Without a script or -e, plogic starts the REPL, or reads the input from stdin when it is not a terminal.");
+    println!("   --no-std:        start without the standard laws std::..");
+    println!("   --load <path>:   load a .plg file before the rest of the input, can be given more than once");
+    println!("   -e <input>:      run one line of input, can be given more than once");
+    println!("   -h, --help:      this usage info");
+    println!("   The exit code is 0 on success, 1 when an input has an error or a check fails, and 2 for invalid arguments.");
 }
 
-pub fn rule_to_string(rule: &Rule, interned: &Vec<String>) -> String {
+pub fn rule_to_string(rule: &Rule, interned: &Interner) -> String {
     match rule {
         Rule::Equivalence(lhs, rhs) => format!("{} = {}", expr_to_string(lhs, interned), expr_to_string(rhs, interned)),
-        Rule::RuleId(n) => format!("{}", interned[*n])
+        Rule::RuleId(n) => interned[*n].to_string()
     }
 }
 
-pub fn expr_to_string(expr: &Expr, interned: &Vec<String>) -> String {
+fn operand_to_string(expr: &Expr, interned: &Interner, parent: &BinOperator, is_right: bool) -> String {
+    match expr {
+        Expr::Binary(_, op, _) if op.precedence() < parent.precedence() || (is_right && op.precedence() == parent.precedence()) => 
+            format!("({})", expr_to_string(expr, interned)),
+        Expr::Quantified(..) => format!("({})", expr_to_string(expr, interned)),
+        _ => expr_to_string(expr, interned),
+    }
+}
+
+pub fn expr_to_string(expr: &Expr, interned: &Interner) -> String {
     match expr {
         Expr::Pattern(e, rule) => 
             format!("{} => {}", expr_to_string(e, interned), rule_to_string(rule, interned)),
         Expr::Binding(id, rule) => 
             format!("{} := {}", expr_to_string(id, interned), rule_to_string(rule, interned)),
-        Expr::Binary(l, op, r) => format!("{} {} {}", expr_to_string(l, interned), op, expr_to_string(r, interned)),
-        Expr::Not(e) => format!("~{}", expr_to_string(e, interned)),
+        Expr::Argument(premises, conclusion) => {
+            let premises = premises.iter().map(|p| expr_to_string(p, interned)).collect::<Vec<_>>();
+            format!("{} |- {}", premises.join(", "), expr_to_string(conclusion, interned)).trim_start().to_string()
+        },
+        Expr::Binary(l, op, r) => format!("{} {} {}", operand_to_string(l, interned, op, false), op, operand_to_string(r, interned, op, true)),
+        Expr::Not(e) => match **e {
+            Expr::Binary(..) | Expr::Quantified(..) => format!("~({})", expr_to_string(e, interned)),
+            _ => format!("~{}", expr_to_string(e, interned)),
+        },
         Expr::Group(e) => format!("({})", expr_to_string(e, interned)),
-        Expr::Primary(n) => format!("{}", interned[*n]),
+        Expr::Quantified(q, n, e) => format!("{} {}. {}", q, interned[*n], expr_to_string(e, interned)),
+        Expr::Primary(n) => interned[*n].to_string(),
+        Expr::Constant(b) => (*b as usize).to_string(),
     }
-}
\ No newline at end of file
+}
+
+// Parses "p=0.3, q=0.9" into values between 0 and 1 for the atoms
+pub fn parse_assignments(input: &str, interned: &mut Interner) -> Result<HashMap<usize, f64>, String> {
+    let mut values = HashMap::new();
+    for assignment in input.split(',') {
+        let Some((name, value)) = assignment.split_once('=') else {
+            return Err(format!("Expected 'atom=value', found '{}'", assignment.trim()));
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("Expected an atom name, found '{}'", name));
+        }
+        match value.trim().parse::<f64>() {
+            Ok(v) if (0.0..=1.0).contains(&v) => values.insert(interned.intern(name), v),
+            _ => return Err(format!("Expected a value between 0 and 1 for {}, found '{}'", name, value.trim())),
+        };
+    }
+    Ok(values)
+}